@@ -1,5 +1,6 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use templatize_core::{CaseShape, FilterSlot, Syntax};
 
 #[derive(Parser)]
 #[command(name = "templatize")]
@@ -15,6 +16,9 @@ pub struct Cli {
 
     #[arg(short, long, global = true)]
     pub quiet: bool,
+
+    #[arg(long, global = true, value_name = "DELIMITERS", help = "Delimiters for the generated template: variable, block and optional comment pairs (e.g. '[[ ]] [% %]'); escape then only escapes collisions with them")]
+    pub syntax: Option<Syntax>,
 }
 
 #[derive(Subcommand)]
@@ -27,6 +31,45 @@ pub enum Commands {
         #[arg(help = "Exact Jinja2 syntax to replace it with")]
         replacement: String,
 
+        #[arg(long, num_args = 2, value_names = ["TOKEN", "REPLACEMENT"], conflicts_with = "ignore_case", help = "Also replace another token in the same pass; the longest token wins where they overlap (repeatable)")]
+        pair: Vec<String>,
+
+        #[arg(long, value_enum, value_name = "BOUNDARY", num_args = 0..=1, require_equals = true, default_missing_value = "unicode", help = "Only replace whole words; 'identifier' also treats '-' as joining words")]
+        word: Option<WordMode>,
+
+        #[command(flatten)]
+        casing: CaseArgs,
+
+        #[arg(long, help = "Also replace inside existing template syntax such as {{ ... }}, for code like format!(\"{{x}}\") that isn't a template")]
+        no_protect: bool,
+
+        #[arg(short, long, help = "Templatize file and directory paths")]
+        path: bool,
+
+        #[arg(short, long, help = "Templatize file contents")]
+        contents: bool,
+
+        #[arg(help = "Target directory (defaults to current directory)")]
+        target: Option<PathBuf>,
+
+        #[arg(long, help = "Perform a dry run without making changes")]
+        dry_run: bool,
+
+        #[arg(short, long, help = "Interactive mode - prompt for each change")]
+        interactive: bool,
+
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+
+    #[command(about = "Replace regular expression matches, with capture group substitution")]
+    Regex {
+        #[arg(help = "Regular expression to match (e.g., 'com\\.acme\\.(\\w+)')")]
+        pattern: String,
+
+        #[arg(help = "Replacement, which may refer to capture groups as $1 or ${name}")]
+        replacement: String,
+
         #[arg(short, long, help = "Templatize file and directory paths")]
         path: bool,
 
@@ -41,16 +84,40 @@ pub enum Commands {
 
         #[arg(short, long, help = "Interactive mode - prompt for each change")]
         interactive: bool,
+
+        #[command(flatten)]
+        filters: FilterArgs,
     },
 
-    #[command(about = "Replace compound words with case shape variants")]
+    #[command(about = "Replace words with case shape variants")]
     Shapes {
-        #[arg(help = "Compound word token to replace (e.g., 'example-name')")]
+        #[arg(help = "Word or compound word to replace (e.g., 'example-name' or 'widget')")]
         token: String,
 
-        #[arg(help = "Compound word Jinja2 replacement (e.g., '{{ project-name }}')")]
+        #[arg(help = "Jinja2 replacement (e.g., '{{ project-name }}')")]
         replacement: String,
 
+        #[arg(long, value_name = "SHAPES", value_delimiter = ',', help = "Only generate these case shapes (e.g. camel,pascal,kebab; defaults to all)")]
+        cases: Vec<CaseShape>,
+
+        #[arg(long = "filters", help = "Keep the replacement's variable and apply a case filter per shape (e.g. '{{ name | camel_case }}')")]
+        use_filters: bool,
+
+        #[arg(long, value_name = "SHAPE=FILTER", value_parser = parse_filter_name, requires = "use_filters", help = "Filter name to use for a shape, plural or singular (e.g. pascal=class_case; repeatable)")]
+        filter_name: Vec<(FilterSlot, String)>,
+
+        #[arg(long, help = "Also replace the plural (or singular) form of the token's last word")]
+        inflect: bool,
+
+        #[arg(long, value_name = "ACRONYMS", value_delimiter = ',', help = "Acronyms to keep together and spell as given (e.g. ID,URL,API,OAuth)")]
+        acronyms: Vec<String>,
+
+        #[arg(long, help = "Also replace inside existing template syntax such as {{ ... }}, for code like format!(\"{{x}}\") that isn't a template")]
+        no_protect: bool,
+
+        #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "text", help = "Print the token to replacement mappings and exit")]
+        print_mappings: Option<MappingFormat>,
+
         #[arg(short, long, help = "Templatize file and directory paths")]
         path: bool,
 
@@ -65,21 +132,180 @@ pub enum Commands {
 
         #[arg(short, long, help = "Interactive mode - prompt for each change")]
         interactive: bool,
+
+        #[command(flatten)]
+        filters: FilterArgs,
     },
 
-    #[command(about = "Escape Jinja2 syntax in file contents")]
+    #[command(about = "Escape Jinja2 syntax in file contents and paths")]
     Escape {
         #[arg(help = "Target file or directory (defaults to current directory)")]
         target: Option<PathBuf>,
 
+        #[arg(short, long, help = "Escape file and directory names")]
+        path: bool,
+
+        #[arg(short, long, help = "Escape file contents (the default unless --path is given)")]
+        contents: bool,
+
         #[arg(long, help = "Perform a dry run without making changes")]
         dry_run: bool,
 
         #[arg(short, long, help = "Interactive mode - prompt for each change")]
         interactive: bool,
+
+        #[arg(long, value_enum, default_value = "auto", help = "How to escape: inline escapes, raw blocks, or raw blocks only for files dense with template syntax")]
+        strategy: StrategyMode,
+
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+
+    #[command(about = "Reverse escaped Jinja2 syntax in file contents and paths")]
+    Unescape {
+        #[arg(help = "Target file or directory (defaults to current directory)")]
+        target: Option<PathBuf>,
+
+        #[arg(short, long, help = "Unescape file and directory names")]
+        path: bool,
+
+        #[arg(short, long, help = "Unescape file contents (the default unless --path is given)")]
+        contents: bool,
+
+        #[arg(long, help = "Perform a dry run without making changes")]
+        dry_run: bool,
+
+        #[arg(short, long, help = "Interactive mode - prompt for each change")]
+        interactive: bool,
+
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+
+    #[command(about = "Revert the most recent templatize operations")]
+    Undo {
+        #[arg(default_value_t = 1, help = "Number of operations to revert")]
+        count: usize,
+
+        #[arg(short = 'C', long, value_name = "TARGET", help = "Project directory holding the journal (defaults to current directory)")]
+        target: Option<PathBuf>,
+
+        #[arg(long, help = "Revert files even if they were modified after the operation")]
+        force: bool,
+    },
+
+    #[command(about = "List the templatize operations recorded for a project")]
+    History {
+        #[arg(help = "Project directory holding the journal (defaults to current directory)")]
+        target: Option<PathBuf>,
     },
 }
 
+/// Case-insensitive matching for `exact`, with replacements per matched casing.
+#[derive(Args, Debug, Default)]
+pub struct CaseArgs {
+    #[arg(short = 'I', long, help = "Match the token in any casing")]
+    pub ignore_case: bool,
+
+    #[arg(long, value_name = "REPLACEMENT", requires = "ignore_case", help = "Replacement for ALL-CAPS matches (e.g. '{{ company | upper }}')")]
+    pub upper: Option<String>,
+
+    #[arg(long, value_name = "REPLACEMENT", requires = "ignore_case", help = "Replacement for all-lowercase matches")]
+    pub lower: Option<String>,
+
+    #[arg(long, value_name = "REPLACEMENT", requires = "ignore_case", help = "Replacement for Title Case matches")]
+    pub title: Option<String>,
+
+    #[arg(long, value_name = "REPLACEMENT", requires = "ignore_case", help = "Replacement for matches in any other casing")]
+    pub mixed: Option<String>,
+}
+
+/// Output format for `--print-mappings`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MappingFormat {
+    Text,
+    Json,
+}
+
+/// Word boundary semantics for `--word`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordMode {
+    /// Letters, digits and '_' join words
+    Unicode,
+    /// Letters, digits, '_' and '-' join words
+    Identifier,
+}
+
+/// Escape strategy for `escape --strategy`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrategyMode {
+    /// Escape each delimiter in place, as in {{'{'}}{ name }}
+    Inline,
+    /// Wrap each run of lines holding template syntax in {% raw %} blocks
+    Raw,
+    /// Raw blocks for files dense with template syntax, inline escapes for the rest
+    Auto,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct FilterArgs {
+    #[arg(long, value_name = "GLOB", help = "Only process files matching this glob (repeatable)")]
+    pub include: Vec<String>,
+
+    #[arg(long, value_name = "GLOB", help = "Skip files and directories matching this glob (repeatable)")]
+    pub exclude: Vec<String>,
+
+    #[arg(long, value_name = "GLOB", help = "Only templatize contents of files matching this glob (repeatable)")]
+    pub contents_in: Vec<String>,
+
+    #[arg(long, value_name = "GLOB", help = "Only templatize paths matching this glob (repeatable)")]
+    pub paths_in: Vec<String>,
+
+    #[arg(long, help = "Don't honor .gitignore, global git excludes or .templatizeignore")]
+    pub no_ignore: bool,
+
+    #[arg(long, value_name = "SIZE", default_value = "10M", value_parser = parse_size, help = "Skip files larger than this (e.g. 512K, 10M, 1G)")]
+    pub max_file_size: u64,
+
+    #[arg(long, value_name = "LABEL", help = "Encoding for text that isn't UTF-8 or UTF-16 (defaults to windows-1252)")]
+    pub encoding: Option<String>,
+}
+
+/// Parses a byte count with an optional K, M or G (binary) suffix.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((index, suffix)) if suffix.is_ascii_alphabetic() => {
+            let multiplier = match suffix.to_ascii_uppercase() {
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                _ => return Err(format!("Unknown size suffix: {}", suffix)),
+            };
+            (&value[..index], multiplier)
+        }
+        _ => (value, 1),
+    };
+    let n = digits
+        .trim()
+        .parse::<u64>()
+        .map_err(|e| format!("Invalid size '{}': {}", value, e))?;
+    n.checked_mul(multiplier)
+        .ok_or_else(|| format!("Invalid size '{}': number too large to fit in target type", value))
+}
+
+/// Parses a `SHAPE=FILTER` pair for `--filter-name`.
+fn parse_filter_name(value: &str) -> Result<(FilterSlot, String), String> {
+    let (shape, filter) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected SHAPE=FILTER, got '{}'", value))?;
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return Err(format!("Missing filter name for '{}'", shape));
+    }
+    Ok((shape.parse()?, filter.to_string()))
+}
+
 impl Cli {
     pub fn parse_args() -> Self {
         Self::parse()
@@ -90,6 +316,7 @@ impl Cli {
 mod tests {
     use super::*;
     use clap::CommandFactory;
+    use templatize_core::Inflection;
 
     #[test]
     fn verify_cli() {
@@ -144,6 +371,99 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_regex_command() {
+        let args = vec![
+            "templatize",
+            "regex",
+            r"com\.acme\.(\w+)",
+            "{{ group_id }}.$1",
+            "-c",
+            "--dry-run",
+        ];
+
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Regex { pattern, replacement, contents, dry_run, .. } => {
+                assert_eq!(pattern, r"com\.acme\.(\w+)");
+                assert_eq!(replacement, "{{ group_id }}.$1");
+                assert!(contents);
+                assert!(dry_run);
+            }
+            _ => panic!("Expected Regex command"),
+        }
+    }
+
+    #[test]
+    fn test_shapes_cases_and_print_mappings() {
+        let args = vec![
+            "templatize",
+            "shapes",
+            "example-name",
+            "{{ project-name }}",
+            "--cases",
+            "camel,dot,upper-flat",
+            "--acronyms",
+            "ID,OAuth",
+            "--print-mappings=json",
+        ];
+
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Shapes { cases, acronyms, print_mappings, .. } => {
+                assert_eq!(cases, [CaseShape::Camel, CaseShape::Dot, CaseShape::UpperFlat]);
+                assert_eq!(acronyms, ["ID", "OAuth"]);
+                assert_eq!(print_mappings, Some(MappingFormat::Json));
+            }
+            _ => panic!("Expected Shapes command"),
+        }
+
+        assert!(Cli::try_parse_from(["templatize", "shapes", "a-b", "c-d", "--cases", "klingon"]).is_err());
+    }
+
+    #[test]
+    fn test_shapes_filter_names() {
+        let args = vec![
+            "templatize",
+            "shapes",
+            "example-name",
+            "{{ project_name }}",
+            "--filters",
+            "--filter-name",
+            "pascal=class_case",
+            "--filter-name",
+            "screaming_snake=upper_snake",
+            "--filter-name",
+            "plural=plural",
+            "--inflect",
+        ];
+
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Shapes { use_filters, filter_name, inflect, .. } => {
+                assert!(use_filters);
+                assert!(inflect);
+                assert_eq!(
+                    filter_name,
+                    [
+                        (FilterSlot::Shape(CaseShape::Pascal), "class_case".to_string()),
+                        (FilterSlot::Shape(CaseShape::ScreamingSnake), "upper_snake".to_string()),
+                        (FilterSlot::Inflection(Inflection::Plural), "plural".to_string()),
+                    ]
+                );
+            }
+            _ => panic!("Expected Shapes command"),
+        }
+
+        let without_filters = ["templatize", "shapes", "a-b", "{{ c }}", "--filter-name", "camel=x"];
+        assert!(Cli::try_parse_from(without_filters).is_err());
+        assert!(parse_filter_name("camel").is_err());
+        assert!(parse_filter_name("camel=").is_err());
+    }
+
     #[test]
     fn test_escape_command() {
         let args = vec![
@@ -156,11 +476,218 @@ mod tests {
         let cli = Cli::try_parse_from(args).unwrap();
         
         match cli.command {
-            Commands::Escape { target, interactive, .. } => {
+            Commands::Escape { target, interactive, strategy, path, contents, .. } => {
                 assert_eq!(target, Some(PathBuf::from("/path/to/file.txt")));
                 assert!(interactive);
+                assert_eq!(strategy, StrategyMode::Auto);
+                assert!(!path && !contents);
             }
             _ => panic!("Expected Escape command"),
         }
+
+        let cli = Cli::try_parse_from(["templatize", "escape", "--path", "-c"]).unwrap();
+        assert!(matches!(cli.command, Commands::Escape { path: true, contents: true, .. }));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unescape_command() {
+        let cli = Cli::try_parse_from(["templatize", "unescape", "project", "-p", "--dry-run"]).unwrap();
+        match cli.command {
+            Commands::Unescape { target, path, contents, dry_run, interactive, .. } => {
+                assert_eq!(target, Some(PathBuf::from("project")));
+                assert!(path && !contents && dry_run && !interactive);
+            }
+            _ => panic!("Expected Unescape command"),
+        }
+    }
+
+    #[test]
+    fn test_no_protect_flag() {
+        let cli = Cli::try_parse_from(["templatize", "exact", "acme", "{{ org }}", "-c", "--no-protect"]).unwrap();
+        assert!(matches!(cli.command, Commands::Exact { no_protect: true, .. }));
+
+        let cli = Cli::try_parse_from(["templatize", "shapes", "acme-corp", "{{ org }}", "-c"]).unwrap();
+        assert!(matches!(cli.command, Commands::Shapes { no_protect: false, .. }));
+    }
+
+    #[test]
+    fn test_syntax_flag() {
+        let cli = Cli::try_parse_from(["templatize", "escape", "--syntax", "[[ ]] [% %]"]).unwrap();
+        let syntax = cli.syntax.unwrap();
+        assert_eq!(syntax.variable, ("[[".to_string(), "]]".to_string()));
+        assert_eq!(syntax.comment, ("[#".to_string(), "#]".to_string()));
+
+        assert!(Cli::try_parse_from(["templatize", "escape", "--syntax", "[[ ]]"]).is_err());
+    }
+
+    #[test]
+    fn test_no_ignore_flag() {
+        let args = vec![
+            "templatize",
+            "exact",
+            "example-name",
+            "{{ project-name }}",
+            "-c",
+            "--no-ignore",
+        ];
+        
+        let cli = Cli::try_parse_from(args).unwrap();
+        
+        match cli.command {
+            Commands::Exact { filters, .. } => assert!(filters.no_ignore),
+            _ => panic!("Expected Exact command"),
+        }
+    }
+
+    #[test]
+    fn test_filter_globs() {
+        let args = vec![
+            "templatize",
+            "shapes",
+            "example-name",
+            "{{ project-name }}",
+            "-p",
+            "-c",
+            "--exclude",
+            "*.lock",
+            "--contents-in",
+            "**/*.java",
+            "--contents-in",
+            "**/*.kt",
+            "--paths-in",
+            "src/**",
+        ];
+        
+        let cli = Cli::try_parse_from(args).unwrap();
+        
+        match cli.command {
+            Commands::Shapes { filters, .. } => {
+                assert_eq!(filters.exclude, vec!["*.lock"]);
+                assert_eq!(filters.contents_in, vec!["**/*.java", "**/*.kt"]);
+                assert_eq!(filters.paths_in, vec!["src/**"]);
+                assert!(filters.include.is_empty());
+            }
+            _ => panic!("Expected Shapes command"),
+        }
+    }
+
+    #[test]
+    fn test_word_flag() {
+        let parse = |args: &[&str]| match Cli::try_parse_from(args).unwrap().command {
+            Commands::Exact { word, target, .. } => (word, target),
+            _ => panic!("Expected Exact command"),
+        };
+
+        assert_eq!(parse(&["templatize", "exact", "app", "{{ app }}", "-c"]), (None, None));
+        assert_eq!(
+            parse(&["templatize", "exact", "app", "{{ app }}", "--word", "src"]),
+            (Some(WordMode::Unicode), Some(PathBuf::from("src")))
+        );
+        assert_eq!(
+            parse(&["templatize", "exact", "app", "{{ app }}", "--word=identifier"]),
+            (Some(WordMode::Identifier), None)
+        );
+    }
+
+    #[test]
+    fn test_exact_pairs() {
+        let args = vec![
+            "templatize",
+            "exact",
+            "Acme",
+            "{{ company }}",
+            "--pair",
+            "Acme Widgets",
+            "{{ product }}",
+            "--pair",
+            "widget",
+            "{{ item }}",
+            "-c",
+        ];
+
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Exact { pair, contents, .. } => {
+                assert_eq!(pair, ["Acme Widgets", "{{ product }}", "widget", "{{ item }}"]);
+                assert!(contents);
+            }
+            _ => panic!("Expected Exact command"),
+        }
+
+        assert!(Cli::try_parse_from(["templatize", "exact", "a", "b", "--pair", "c"]).is_err());
+        assert!(Cli::try_parse_from(["templatize", "exact", "a", "b", "--pair", "c", "d", "-I"]).is_err());
+    }
+
+    #[test]
+    fn test_case_flags() {
+        let args = vec![
+            "templatize",
+            "exact",
+            "Acme Corp",
+            "{{ company }}",
+            "-c",
+            "-I",
+            "--upper",
+            "{{ company | upper }}",
+        ];
+
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Commands::Exact { casing, .. } => {
+                assert!(casing.ignore_case);
+                assert_eq!(casing.upper.as_deref(), Some("{{ company | upper }}"));
+                assert_eq!(casing.lower, None);
+            }
+            _ => panic!("Expected Exact command"),
+        }
+
+        let without_ignore_case = ["templatize", "exact", "Acme", "{{ company }}", "--lower", "x"];
+        assert!(Cli::try_parse_from(without_ignore_case).is_err());
+    }
+
+    #[test]
+    fn test_undo_command() {
+        let cli = Cli::try_parse_from(["templatize", "undo"]).unwrap();
+        match cli.command {
+            Commands::Undo { count, target, force } => {
+                assert_eq!(count, 1);
+                assert_eq!(target, None);
+                assert!(!force);
+            }
+            _ => panic!("Expected Undo command"),
+        }
+
+        let cli = Cli::try_parse_from(["templatize", "undo", "3", "-C", "my-project", "--force"]).unwrap();
+        match cli.command {
+            Commands::Undo { count, target, force } => {
+                assert_eq!(count, 3);
+                assert_eq!(target, Some(PathBuf::from("my-project")));
+                assert!(force);
+            }
+            _ => panic!("Expected Undo command"),
+        }
+
+        let cli = Cli::try_parse_from(["templatize", "undo", "--target", "my-project"]).unwrap();
+        match cli.command {
+            Commands::Undo { count, target, .. } => {
+                assert_eq!(count, 1);
+                assert_eq!(target, Some(PathBuf::from("my-project")));
+            }
+            _ => panic!("Expected Undo command"),
+        }
+        assert!(Cli::try_parse_from(["templatize", "undo", "my-project"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("4k").unwrap(), 4096);
+        assert_eq!(parse_size("10M").unwrap(), 10 * 1024 * 1024);
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("big").is_err());
+        assert!(parse_size("18446744073709551615K").is_err());
+        assert_eq!(parse_size("16777215G").unwrap(), 16777215 << 30);
+    }
+}
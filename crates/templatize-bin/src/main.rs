@@ -2,8 +2,15 @@ mod cli;
 mod diff;
 
 use anyhow::Result;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, FilterArgs, MappingFormat, StrategyMode, WordMode};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use templatize_core::{
+    CaseFilters, CaseReplacements, Dialect, DialectReport, EscapeStrategy, Journal, ShapeOptions, Syntax, TemplateOptions, WordBoundary,
+};
 use tracing::{info, warn};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
@@ -13,119 +20,244 @@ fn main() -> Result<()> {
     setup_logging(&cli)?;
     
     info!("Starting templatize");
-    
+
+    let cancel_flag = install_interrupt_handler()?;
+    let syntax = cli.syntax.unwrap_or_default();
+    let templates = !matches!(cli.command, Commands::Unescape { .. } | Commands::Undo { .. } | Commands::History { .. });
+
     match cli.command {
         Commands::Exact { 
             token, 
             replacement, 
+            pair,
+            word,
+            casing,
+            no_protect,
             path, 
             contents, 
             target, 
             dry_run,
-            interactive 
+            interactive,
+            filters,
         } => {
-            // Validate that at least one of -p or -c is specified
-            if !path && !contents {
-                use inquire::MultiSelect;
-                
-                let options = vec![
-                    "Path templating (-p)",
-                    "Contents templating (-c)",
-                ];
-                
-                let selected = MultiSelect::new("Select templating options:", options)
-                    .with_default(&[0, 1]) // Both options selected by default
-                    .prompt()?;
-                
-                if selected.is_empty() {
-                    anyhow::bail!("At least one templating option must be selected");
-                }
-                
-                let enable_path = selected.contains(&"Path templating (-p)");
-                let enable_contents = selected.contains(&"Contents templating (-c)");
-                
-                return handle_exact_command(
-                    token, 
-                    replacement, 
-                    enable_path, 
-                    enable_contents, 
-                    target, 
-                    dry_run,
-                    interactive
-                );
-            }
-            
-            handle_exact_command(token, replacement, path, contents, target, dry_run, interactive)?;
+            let (path, contents) = select_templating_modes(path, contents)?;
+            let options = TemplateOptions {
+                word_boundary: match word {
+                    None => WordBoundary::None,
+                    Some(WordMode::Unicode) => WordBoundary::Unicode,
+                    Some(WordMode::Identifier) => WordBoundary::Identifier,
+                },
+                ignore_case: casing.ignore_case,
+                case_replacements: CaseReplacements {
+                    upper: casing.upper,
+                    lower: casing.lower,
+                    title: casing.title,
+                    mixed: casing.mixed,
+                },
+                no_protect,
+                ..template_options(path, contents, dry_run, filters, &syntax, &cancel_flag)
+            };
+            // `--pair` takes exactly two values each time, so they always come in twos
+            let pairs = pair.chunks_exact(2).map(|pair| (pair[0].clone(), pair[1].clone()));
+            let pairs: Vec<_> = std::iter::once((token, replacement)).chain(pairs).collect();
+            handle_exact_command(pairs, target, options, interactive)?;
+        }
+        Commands::Regex {
+            pattern,
+            replacement,
+            path,
+            contents,
+            target,
+            dry_run,
+            interactive,
+            filters,
+        } => {
+            let (path, contents) = select_templating_modes(path, contents)?;
+            let options = template_options(path, contents, dry_run, filters, &syntax, &cancel_flag);
+            handle_regex_command(pattern, replacement, target, options, interactive)?;
         }
         Commands::Shapes { 
             token, 
             replacement, 
+            cases,
+            use_filters,
+            filter_name,
+            inflect,
+            acronyms,
+            no_protect,
+            print_mappings,
             path, 
             contents, 
             target, 
             dry_run,
-            interactive 
+            interactive,
+            filters,
         } => {
-            // Validate that at least one of -p or -c is specified
-            if !path && !contents {
-                use inquire::MultiSelect;
-                
-                let options = vec![
-                    "Path templating (-p)",
-                    "Contents templating (-c)",
-                ];
-                
-                let selected = MultiSelect::new("Select templating options:", options)
-                    .with_default(&[0, 1]) // Both options selected by default
-                    .prompt()?;
-                
-                if selected.is_empty() {
-                    anyhow::bail!("At least one templating option must be selected");
-                }
-                
-                let enable_path = selected.contains(&"Path templating (-p)");
-                let enable_contents = selected.contains(&"Contents templating (-c)");
-                
-                return handle_shapes_command(
-                    token, 
-                    replacement, 
-                    enable_path, 
-                    enable_contents, 
-                    target, 
-                    dry_run,
-                    interactive
-                );
+            let shapes = ShapeOptions {
+                shapes: cases,
+                filters: use_filters.then(|| {
+                    filter_name
+                        .iter()
+                        .fold(CaseFilters::default(), |filters, (slot, name)| filters.with_name(*slot, name))
+                }),
+                inflect,
+                acronyms,
+            };
+            if let Some(format) = print_mappings {
+                let options = TemplateOptions {
+                    shapes,
+                    syntax,
+                    ..TemplateOptions::default()
+                };
+                return print_shape_mappings(&token, &replacement, &options, format);
             }
-            
-            handle_shapes_command(token, replacement, path, contents, target, dry_run, interactive)?;
+            let (path, contents) = select_templating_modes(path, contents)?;
+            let options = TemplateOptions {
+                shapes,
+                no_protect,
+                ..template_options(path, contents, dry_run, filters, &syntax, &cancel_flag)
+            };
+            handle_shapes_command(token, replacement, target, options, interactive)?;
+        }
+        Commands::Escape { target, path, contents, dry_run, interactive, strategy, filters } => {
+            // Escaping contents is the default, so only `--path` on its own leaves them alone
+            let contents = contents || !path;
+            let options = TemplateOptions {
+                escape_strategy: match strategy {
+                    StrategyMode::Inline => EscapeStrategy::Inline,
+                    StrategyMode::Raw => EscapeStrategy::Raw,
+                    StrategyMode::Auto => EscapeStrategy::Auto,
+                },
+                ..template_options(path, contents, dry_run, filters, &syntax, &cancel_flag)
+            };
+            handle_escape_command(target, options, interactive)?;
         }
-        Commands::Escape { target, dry_run, interactive } => {
-            handle_escape_command(target, dry_run, interactive)?;
+        Commands::Unescape { target, path, contents, dry_run, interactive, filters } => {
+            let contents = contents || !path;
+            let options = template_options(path, contents, dry_run, filters, &syntax, &cancel_flag);
+            handle_unescape_command(target, options, interactive)?;
+        }
+        Commands::Undo { count, target, force } => {
+            handle_undo_command(count, target, force)?;
+        }
+        Commands::History { target } => {
+            handle_history_command(target)?;
         }
     }
     
+    if templates && !syntax.is_standard() {
+        println!();
+        println!("The template uses the delimiters '{}'. Render it with:", syntax);
+        println!("{}", syntax.renderer_config());
+    }
+
     info!("Templatize completed successfully");
     Ok(())
 }
 
-fn handle_exact_command(
-    token: String,
-    replacement: String,
+/// Makes the first Ctrl-C stop the run so it can roll back; a second one exits immediately.
+fn install_interrupt_handler() -> Result<Arc<AtomicBool>> {
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = cancel_flag.clone();
+    ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        eprintln!("Interrupted, rolling back changes (press Ctrl-C again to abort immediately)");
+    })?;
+    Ok(cancel_flag)
+}
+
+fn template_options(
     path: bool,
     contents: bool,
-    target: Option<PathBuf>,
     dry_run: bool,
+    filters: FilterArgs,
+    syntax: &Syntax,
+    cancel_flag: &Arc<AtomicBool>,
+) -> TemplateOptions {
+    TemplateOptions {
+        process_paths: path,
+        process_contents: contents,
+        dry_run,
+        no_ignore: filters.no_ignore,
+        include: filters.include,
+        exclude: filters.exclude,
+        content_scope: filters.contents_in,
+        path_scope: filters.paths_in,
+        max_file_size: Some(filters.max_file_size),
+        fallback_encoding: filters.encoding,
+        cancel_flag: Some(cancel_flag.clone()),
+        journal: Some(command_line()),
+        word_boundary: WordBoundary::None,
+        ignore_case: false,
+        case_replacements: CaseReplacements::default(),
+        shapes: ShapeOptions::default(),
+        syntax: syntax.clone(),
+        no_protect: false,
+        escape_strategy: EscapeStrategy::default(),
+    }
+}
+
+/// The arguments of this invocation, quoted where needed, for the journal.
+fn command_line() -> String {
+    std::env::args()
+        .skip(1)
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("'{}'", arg)
+            } else {
+                arg
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Prompts for path/contents templating when neither `-p` nor `-c` was given.
+fn select_templating_modes(path: bool, contents: bool) -> Result<(bool, bool)> {
+    if path || contents {
+        return Ok((path, contents));
+    }
+
+    use inquire::MultiSelect;
+    
+    let options = vec![
+        "Path templating (-p)",
+        "Contents templating (-c)",
+    ];
+    
+    let selected = MultiSelect::new("Select templating options:", options)
+        .with_default(&[0, 1]) // Both options selected by default
+        .prompt()?;
+    
+    if selected.is_empty() {
+        anyhow::bail!("At least one templating option must be selected");
+    }
+    
+    let enable_path = selected.contains(&"Path templating (-p)");
+    let enable_contents = selected.contains(&"Contents templating (-c)");
+    
+    Ok((enable_path, enable_contents))
+}
+
+fn handle_exact_command(
+    pairs: Vec<(String, String)>,
+    target: Option<PathBuf>,
+    options: TemplateOptions,
     interactive: bool,
 ) -> Result<()> {
     let target_dir = target.unwrap_or_else(|| std::env::current_dir().unwrap());
     
-    info!("Exact replacement: '{}' -> '{}'", token, replacement);
+    for (token, replacement) in &pairs {
+        info!("Exact replacement: '{}' -> '{}'", token, replacement);
+    }
     info!("Target directory: {:?}", target_dir);
-    info!("Path templating: {}", path);
-    info!("Contents templating: {}", contents);
+    info!("Path templating: {}", options.process_paths);
+    info!("Contents templating: {}", options.process_contents);
     info!("Interactive mode: {}", interactive);
     
-    if dry_run {
+    if options.dry_run {
         warn!("Dry run mode - no changes will be made");
     }
     
@@ -147,53 +279,113 @@ fn handle_exact_command(
             diff::show_path_change_and_confirm(old_path, new_path, change_type)
         };
         
-        templatize_core::process_directory_interactive(
+        match &pairs[..] {
+            [(token, replacement)] => templatize_core::process_directory_interactive(
+                &target_dir,
+                token,
+                replacement,
+                &options,
+                content_callback,
+                path_callback,
+            )?,
+            _ => templatize_core::process_directory_pairs_interactive(
+                &target_dir,
+                &pairs,
+                &options,
+                content_callback,
+                path_callback,
+            )?,
+        }
+    } else {
+        match &pairs[..] {
+            [(token, replacement)] => templatize_core::process_directory(&target_dir, token, replacement, &options)?,
+            _ => templatize_core::process_directory_pairs(&target_dir, &pairs, &options)?,
+        }
+    };
+    
+    println!("Templating complete!");
+    println!("  Files processed: {}", result.files_processed);
+    println!("  Paths renamed: {}", result.paths_renamed);
+    println!("  Content changes: {}", result.content_changes);
+    println!("  Files skipped: {}", result.files_skipped);
+    print_protected_summary(result.protected_occurrences);
+    
+    Ok(())
+}
+
+fn handle_regex_command(
+    pattern: String,
+    replacement: String,
+    target: Option<PathBuf>,
+    options: TemplateOptions,
+    interactive: bool,
+) -> Result<()> {
+    let target_dir = target.unwrap_or_else(|| std::env::current_dir().unwrap());
+
+    info!("Regex replacement: '{}' -> '{}'", pattern, replacement);
+    info!("Target directory: {:?}", target_dir);
+    info!("Path templating: {}", options.process_paths);
+    info!("Contents templating: {}", options.process_contents);
+    info!("Interactive mode: {}", interactive);
+
+    if options.dry_run {
+        warn!("Dry run mode - no changes will be made");
+    }
+
+    if !target_dir.exists() {
+        anyhow::bail!("Target directory does not exist: {:?}", target_dir);
+    }
+
+    if !target_dir.is_dir() {
+        anyhow::bail!("Target must be a directory: {:?}", target_dir);
+    }
+
+    let result = if interactive {
+        let content_callback = |file_path: &std::path::Path, old_content: &str, new_content: &str, description: &str| {
+            diff::show_diff_and_confirm(file_path, old_content, new_content, description)
+        };
+
+        let path_callback = |old_path: &std::path::Path, new_path: &std::path::Path, change_type: &str| {
+            diff::show_path_change_and_confirm(old_path, new_path, change_type)
+        };
+
+        templatize_core::process_directory_regex_interactive(
             &target_dir,
-            &token,
+            &pattern,
             &replacement,
-            path,
-            contents,
-            dry_run,
+            &options,
             content_callback,
             path_callback,
         )?
     } else {
-        templatize_core::process_directory(
-            &target_dir,
-            &token,
-            &replacement,
-            path,
-            contents,
-            dry_run,
-        )?
+        templatize_core::process_directory_regex(&target_dir, &pattern, &replacement, &options)?
     };
-    
-    println!("Templating complete!");
+
+    println!("Regex templating complete!");
     println!("  Files processed: {}", result.files_processed);
     println!("  Paths renamed: {}", result.paths_renamed);
     println!("  Content changes: {}", result.content_changes);
-    
+    println!("  Files skipped: {}", result.files_skipped);
+
     Ok(())
 }
 
 fn handle_shapes_command(
     token: String,
     replacement: String,
-    path: bool,
-    contents: bool,
     target: Option<PathBuf>,
-    dry_run: bool,
+    options: TemplateOptions,
     interactive: bool,
 ) -> Result<()> {
     let target_dir = target.unwrap_or_else(|| std::env::current_dir().unwrap());
     
     info!("Shapes replacement: '{}' -> '{}'", token, replacement);
     info!("Target directory: {:?}", target_dir);
-    info!("Path templating: {}", path);
-    info!("Contents templating: {}", contents);
+    info!("Path templating: {}", options.process_paths);
+    info!("Contents templating: {}", options.process_contents);
     info!("Interactive mode: {}", interactive);
     
-    if dry_run {
+    if options.dry_run {
         warn!("Dry run mode - no changes will be made");
     }
     
@@ -219,9 +411,7 @@ fn handle_shapes_command(
             &target_dir,
             &token,
             &replacement,
-            path,
-            contents,
-            dry_run,
+            &options,
             content_callback,
             path_callback,
         )?
@@ -230,9 +420,7 @@ fn handle_shapes_command(
             &target_dir,
             &token,
             &replacement,
-            path,
-            contents,
-            dry_run,
+            &options,
         )?
     };
     
@@ -240,17 +428,65 @@ fn handle_shapes_command(
     println!("  Files processed: {}", result.files_processed);
     println!("  Paths renamed: {}", result.paths_renamed);
     println!("  Content changes: {}", result.content_changes);
+    println!("  Files skipped: {}", result.files_skipped);
+    print_protected_summary(result.protected_occurrences);
     
     Ok(())
 }
 
-fn handle_escape_command(target: Option<PathBuf>, dry_run: bool, interactive: bool) -> Result<()> {
+/// Points out occurrences left alone inside existing template syntax, which would otherwise
+/// go unnoticed.
+fn print_protected_summary(occurrences: usize) {
+    if occurrences > 0 {
+        println!(
+            "  Left inside existing template syntax: {} (use --no-protect to replace them too)",
+            occurrences
+        );
+    }
+}
+
+fn print_shape_mappings(
+    token: &str,
+    replacement: &str,
+    options: &TemplateOptions,
+    format: MappingFormat,
+) -> Result<()> {
+    let templater = templatize_core::shapes_templater(token, replacement, options)?;
+    let mappings = templater.get_mappings();
+
+    match format {
+        MappingFormat::Json => println!("{}", serde_json::to_string_pretty(&mappings)?),
+        MappingFormat::Text => {
+            let shape_names: Vec<_> = mappings
+                .iter()
+                .map(|m| match m.inflection {
+                    Some(inflection) => format!("{} {}", inflection, m.shape),
+                    None => m.shape.clone(),
+                })
+                .collect();
+            let shape_width = shape_names.iter().map(String::len).max().unwrap_or(0);
+            let token_width = mappings.iter().map(|m| m.original.chars().count()).max().unwrap_or(0);
+            for (shape, mapping) in shape_names.iter().zip(&mappings) {
+                println!(
+                    "{:<shape_width$}  {:<token_width$}  ->  {}",
+                    shape, mapping.original, mapping.replacement
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_escape_command(target: Option<PathBuf>, options: TemplateOptions, interactive: bool) -> Result<()> {
     let target_path = target.unwrap_or_else(|| std::env::current_dir().unwrap());
     
     info!("Jinja escaping for: {:?}", target_path);
+    info!("Path escaping: {}", options.process_paths);
+    info!("Contents escaping: {}", options.process_contents);
     info!("Interactive mode: {}", interactive);
     
-    if dry_run {
+    if options.dry_run {
         warn!("Dry run mode - no changes will be made");
     }
     
@@ -260,22 +496,127 @@ fn handle_escape_command(target: Option<PathBuf>, dry_run: bool, interactive: bo
     
     // Use the core escaping functionality
     let result = if interactive {
-        let callback = |file_path: &std::path::Path, old_content: &str, new_content: &str, description: &str| {
+        let content_callback = |file_path: &std::path::Path, old_content: &str, new_content: &str, description: &str| {
             diff::show_diff_and_confirm(file_path, old_content, new_content, description)
         };
         
-        templatize_core::escape_jinja_syntax_interactive(&target_path, dry_run, callback)?
+        let path_callback = |old_path: &std::path::Path, new_path: &std::path::Path, change_type: &str| {
+            diff::show_path_change_and_confirm(old_path, new_path, change_type)
+        };
+        
+        templatize_core::escape_jinja_syntax_interactive(&target_path, &options, content_callback, path_callback)?
     } else {
-        templatize_core::escape_jinja_syntax(&target_path, dry_run)?
+        templatize_core::escape_jinja_syntax(&target_path, &options)?
     };
     
     println!("Jinja escaping complete!");
     println!("  Files processed: {}", result.files_processed);
+    println!("  Paths renamed: {}", result.paths_renamed);
     println!("  Content changes: {}", result.content_changes);
+    println!("  Files skipped: {}", result.files_skipped);
+    print_dialect_summary(&result.dialects);
     
     Ok(())
 }
 
+fn handle_unescape_command(target: Option<PathBuf>, options: TemplateOptions, interactive: bool) -> Result<()> {
+    let target_path = target.unwrap_or_else(|| std::env::current_dir().unwrap());
+
+    info!("Jinja unescaping for: {:?}", target_path);
+    info!("Path unescaping: {}", options.process_paths);
+    info!("Contents unescaping: {}", options.process_contents);
+    info!("Interactive mode: {}", interactive);
+
+    if options.dry_run {
+        warn!("Dry run mode - no changes will be made");
+    }
+
+    if !target_path.exists() {
+        anyhow::bail!("Target does not exist: {:?}", target_path);
+    }
+
+    let result = if interactive {
+        let content_callback = |file_path: &std::path::Path, old_content: &str, new_content: &str, description: &str| {
+            diff::show_diff_and_confirm(file_path, old_content, new_content, description)
+        };
+
+        let path_callback = |old_path: &std::path::Path, new_path: &std::path::Path, change_type: &str| {
+            diff::show_path_change_and_confirm(old_path, new_path, change_type)
+        };
+
+        templatize_core::unescape_jinja_syntax_interactive(&target_path, &options, content_callback, path_callback)?
+    } else {
+        templatize_core::unescape_jinja_syntax(&target_path, &options)?
+    };
+
+    println!("Jinja unescaping complete!");
+    println!("  Files processed: {}", result.files_processed);
+    println!("  Paths renamed: {}", result.paths_renamed);
+    println!("  Content changes: {}", result.content_changes);
+    println!("  Files skipped: {}", result.files_skipped);
+
+    Ok(())
+}
+
+/// Lists the files holding each template dialect that escape found, and how each was escaped.
+fn print_dialect_summary(reports: &[DialectReport]) {
+    let mut by_dialect: BTreeMap<Dialect, Vec<&DialectReport>> = BTreeMap::new();
+    for report in reports {
+        for &dialect in &report.dialects {
+            by_dialect.entry(dialect).or_default().push(report);
+        }
+    }
+    if by_dialect.is_empty() {
+        return;
+    }
+
+    println!("Template dialects found:");
+    for (dialect, reports) in &by_dialect {
+        let files = if reports.len() == 1 { "file" } else { "files" };
+        println!("  {}: {} {}", dialect, reports.len(), files);
+        for report in reports {
+            println!("    {} ({})", report.path.display(), report.plan);
+        }
+    }
+}
+
+fn handle_undo_command(count: usize, target: Option<PathBuf>, force: bool) -> Result<()> {
+    let target_dir = target.unwrap_or_else(|| std::env::current_dir().unwrap());
+    let (undone, root) = Journal::new(&target_dir).undo(count, force)?;
+
+    for operation in &undone {
+        println!("Undid operation {}: {}", operation.id, operation.description);
+    }
+    if root != target_dir {
+        println!("Project directory restored to {:?}", root);
+    }
+
+    Ok(())
+}
+
+fn handle_history_command(target: Option<PathBuf>) -> Result<()> {
+    let target_dir = target.unwrap_or_else(|| std::env::current_dir().unwrap());
+    let operations = Journal::new(&target_dir).operations()?;
+
+    if operations.is_empty() {
+        println!("No operations recorded");
+        return Ok(());
+    }
+    for operation in operations.iter().rev() {
+        let time = UNIX_EPOCH + Duration::from_secs(operation.timestamp);
+        println!(
+            "{:>4}  {}  {}  ({} files changed, {} paths renamed)",
+            operation.id,
+            humantime::format_rfc3339_seconds(time),
+            operation.description,
+            operation.files_written(),
+            operation.paths_renamed(),
+        );
+    }
+
+    Ok(())
+}
+
 fn setup_logging(cli: &Cli) -> Result<()> {
     let filter = if cli.quiet {
         EnvFilter::new("error")
@@ -288,6 +629,7 @@ fn setup_logging(cli: &Cli) -> Result<()> {
     tracing_subscriber::registry()
         .with(
             fmt::layer()
+                .with_writer(std::io::stderr)
                 .with_target(false)
                 .with_thread_ids(false)
                 .with_thread_names(false)
@@ -0,0 +1,134 @@
+use encoding_rs::Encoding;
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::text::TextHint;
+
+/// The subset of `.gitattributes` that affects how file contents are read.
+///
+/// Files are loaded lazily from every directory between the walk root and the file in
+/// question. Later lines and deeper files override earlier ones, as in git.
+pub struct GitAttributes {
+    root: PathBuf,
+    cache: HashMap<PathBuf, Vec<AttributeRule>>,
+}
+
+struct AttributeRule {
+    matcher: GlobMatcher,
+    /// `Some(Some(true))` marks binary, `Some(Some(false))` text, `Some(None)` auto-detect.
+    binary: Option<Option<bool>>,
+    encoding: Option<&'static Encoding>,
+}
+
+impl GitAttributes {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Looks up the text hints for `relative`, a path relative to the walk root.
+    pub fn hint(&mut self, relative: &Path) -> TextHint {
+        let mut hint = TextHint::default();
+        let mut dir = PathBuf::new();
+        let mut components = relative.components().peekable();
+
+        while let Some(component) = components.next() {
+            let rules = self
+                .cache
+                .entry(dir.clone())
+                .or_insert_with(|| load_rules(&self.root.join(&dir)));
+            let local = relative.strip_prefix(&dir).unwrap_or(relative);
+            for rule in rules.iter().filter(|rule| rule.matcher.is_match(local)) {
+                if let Some(binary) = rule.binary {
+                    hint.binary = binary;
+                }
+                if rule.encoding.is_some() {
+                    hint.encoding = rule.encoding;
+                }
+            }
+
+            if components.peek().is_none() {
+                break;
+            }
+            dir.push(component);
+        }
+
+        hint
+    }
+}
+
+fn load_rules(dir: &Path) -> Vec<AttributeRule> {
+    let Ok(contents) = fs::read_to_string(dir.join(".gitattributes")) else {
+        return Vec::new();
+    };
+    contents.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<AttributeRule> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let mut parts = line.split_whitespace();
+    let pattern = parts.next()?;
+    let pattern = if pattern.trim_end_matches('/').contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    let matcher = match GlobBuilder::new(&pattern).literal_separator(true).build() {
+        Ok(glob) => glob.compile_matcher(),
+        Err(e) => {
+            warn!("Ignoring invalid .gitattributes pattern '{}': {}", pattern, e);
+            return None;
+        }
+    };
+
+    let mut rule = AttributeRule {
+        matcher,
+        binary: None,
+        encoding: None,
+    };
+    for attribute in parts {
+        match attribute {
+            "binary" | "-text" => rule.binary = Some(Some(true)),
+            "text" => rule.binary = Some(Some(false)),
+            "text=auto" | "!text" => rule.binary = Some(None),
+            _ => {
+                if let Some(label) = attribute.strip_prefix("working-tree-encoding=") {
+                    rule.encoding = Encoding::for_label(label.as_bytes());
+                }
+            }
+        }
+    }
+    Some(rule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_gitattributes_override() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("docs")).unwrap();
+        fs::write(temp.path().join(".gitattributes"), "*.dat binary\n*.txt working-tree-encoding=UTF-16LE\n").unwrap();
+        fs::write(temp.path().join("docs/.gitattributes"), "special.dat text\n").unwrap();
+
+        let mut attributes = GitAttributes::new(temp.path());
+
+        assert_eq!(attributes.hint(Path::new("data/blob.dat")).binary, Some(true));
+        assert_eq!(attributes.hint(Path::new("docs/special.dat")).binary, Some(false));
+        assert_eq!(attributes.hint(Path::new("docs/readme.md")).binary, None);
+        assert_eq!(
+            attributes.hint(Path::new("notes.txt")).encoding,
+            Some(encoding_rs::UTF_16LE)
+        );
+    }
+}
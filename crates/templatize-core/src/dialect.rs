@@ -0,0 +1,270 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::jinja::{EscapeStrategy, Segment, SegmentKind};
+
+/// Extensions of files that are Jinja-like templates themselves, which are wrapped in a single
+/// raw block unless escaping inline.
+pub const TEMPLATE_EXTENSIONS: &[&str] = &["j2", "jinja", "jinja2", "tera"];
+
+/// Keywords that start a Go template action, as in `{{ include "name" . }}` or `{{ end }}`.
+const GO_KEYWORDS: &[&str] = &[
+    "define", "template", "include", "block", "end", "if", "else", "range", "with", "not", "and", "or", "eq", "ne",
+    "printf", "tpl", "toYaml",
+];
+
+/// Block helpers that only Handlebars has, as opposed to plain Mustache sections.
+const HANDLEBARS_HELPERS: &[&str] = &["each", "if", "unless", "with", "let", "lookup"];
+
+/// A template language whose `{{ }}` syntax collides with Jinja's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dialect {
+    Jinja,
+    /// GitHub Actions expressions such as `${{ github.sha }}`.
+    GitHubActions,
+    /// Go templates, including Helm charts, such as `{{ .Values.image }}`.
+    GoTemplate,
+    /// Handlebars templates such as `{{#each items}}`.
+    Handlebars,
+    /// Mustache templates such as `{{> partial}}` or `{{^empty}}`.
+    Mustache,
+}
+
+impl Dialect {
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Jinja => "Jinja",
+            Dialect::GitHubActions => "GitHub Actions",
+            Dialect::GoTemplate => "Go template",
+            Dialect::Handlebars => "Handlebars",
+            Dialect::Mustache => "Mustache",
+        }
+    }
+
+    /// How files in this dialect are escaped when no explicit inline strategy was chosen: whole
+    /// templates go in a single raw block, and expressions scattered through workflows get raw
+    /// blocks around the lines that hold them.
+    fn escape_plan(self, strategy: EscapeStrategy) -> EscapePlan {
+        match self {
+            Dialect::Jinja => EscapePlan::Strategy(strategy),
+            Dialect::GitHubActions => EscapePlan::Strategy(EscapeStrategy::Raw),
+            Dialect::GoTemplate | Dialect::Handlebars | Dialect::Mustache => EscapePlan::WholeFile,
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How the contents of a file are escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapePlan {
+    Strategy(EscapeStrategy),
+    /// The whole file in one raw block.
+    WholeFile,
+}
+
+impl fmt::Display for EscapePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EscapePlan::Strategy(EscapeStrategy::Inline) => "inline escapes",
+            EscapePlan::Strategy(EscapeStrategy::Raw) => "raw blocks around template lines",
+            EscapePlan::Strategy(EscapeStrategy::Auto) => "inline escapes or raw blocks by density",
+            EscapePlan::WholeFile => "one raw block for the whole file",
+        })
+    }
+}
+
+/// The template dialects found in a file by `escape`, and how it was escaped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DialectReport {
+    pub path: PathBuf,
+    pub dialects: Vec<Dialect>,
+    #[serde(skip)]
+    pub plan: EscapePlan,
+}
+
+impl DialectReport {
+    /// The dialects of the file at `relative`, which holds opening delimiters that need
+    /// escaping and was split into `segments` by [`jinja::lex`].
+    ///
+    /// Dialects are recognized by the file's location (workflows, `.tpl` and `.hbs` files and
+    /// the like) and by the tags themselves; tags that match no other dialect count as Jinja.
+    pub fn detect(relative: &Path, content: &str, segments: &[Segment], strategy: EscapeStrategy) -> Self {
+        let mut dialects: Vec<Dialect> = location_dialect(relative).into_iter().collect();
+        let mut plain_names = false;
+        for tag in segments {
+            dialects.push(match tag.kind {
+                SegmentKind::Text => continue,
+                SegmentKind::Expression => {
+                    let inner = tag.inner(content).trim();
+                    let after_dollar = content[..tag.range.start].ends_with('$');
+                    if !after_dollar && is_plain_name(inner) {
+                        plain_names = true;
+                        continue;
+                    }
+                    expression_dialect(inner, after_dollar)
+                }
+                _ => Dialect::Jinja,
+            });
+        }
+        if dialects.contains(&Dialect::Handlebars) {
+            dialects.retain(|&dialect| dialect != Dialect::Mustache);
+        }
+        // `{{ name }}` reads the same in Jinja, Handlebars and Mustache, so it only counts as
+        // Jinja when neither of the others was found. A file with nothing but unclosed openers
+        // counts as Jinja too, since that's what would choke on them.
+        let mustache_like = dialects.iter().any(|dialect| matches!(dialect, Dialect::Handlebars | Dialect::Mustache));
+        if (plain_names && !mustache_like) || dialects.is_empty() {
+            dialects.push(Dialect::Jinja);
+        }
+        dialects.sort();
+        dialects.dedup();
+
+        let extension = relative.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        let plan = if strategy == EscapeStrategy::Inline {
+            EscapePlan::Strategy(strategy)
+        } else if TEMPLATE_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+            EscapePlan::WholeFile
+        } else {
+            // The most constrained dialect decides
+            dialects
+                .iter()
+                .map(|dialect| dialect.escape_plan(strategy))
+                .max_by_key(|plan| match plan {
+                    EscapePlan::WholeFile => 2,
+                    EscapePlan::Strategy(EscapeStrategy::Raw) => 1,
+                    EscapePlan::Strategy(_) => 0,
+                })
+                .unwrap_or(EscapePlan::Strategy(strategy))
+        };
+        Self {
+            path: relative.to_path_buf(),
+            dialects,
+            plan,
+        }
+    }
+
+    /// Whether the file holds anything other than Jinja.
+    pub fn is_foreign(&self) -> bool {
+        self.dialects.iter().any(|&dialect| dialect != Dialect::Jinja)
+    }
+}
+
+/// The dialect implied by where a file lives or what it's called.
+fn location_dialect(relative: &Path) -> Option<Dialect> {
+    let extension = relative.extension().and_then(|extension| extension.to_str())?.to_lowercase();
+    let file_name = relative.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let in_workflows = relative.parent().is_some_and(|parent| parent.ends_with(".github/workflows"));
+    match extension.as_str() {
+        "yml" | "yaml" if in_workflows || file_name.starts_with("action.") => Some(Dialect::GitHubActions),
+        "tpl" | "gotmpl" => Some(Dialect::GoTemplate),
+        "hbs" | "handlebars" => Some(Dialect::Handlebars),
+        "mustache" => Some(Dialect::Mustache),
+        _ => None,
+    }
+}
+
+/// Whether `inner` is a bare, possibly dotted, variable name such as `name` or `item.id`, as
+/// opposed to Go's `{{ end }}`.
+fn is_plain_name(inner: &str) -> bool {
+    inner != "end"
+        && inner.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && inner.chars().all(|c| c.is_alphanumeric() || "_.-".contains(c))
+}
+
+/// The dialect of the expression whose inner text is `inner`.
+fn expression_dialect(inner: &str, after_dollar: bool) -> Dialect {
+    if after_dollar {
+        return Dialect::GitHubActions;
+    }
+    let first_word = |text: &str| -> String {
+        text.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect()
+    };
+    if let Some(section) = inner.strip_prefix(['#', '/']) {
+        return if HANDLEBARS_HELPERS.contains(&first_word(section).as_str()) {
+            Dialect::Handlebars
+        } else {
+            Dialect::Mustache
+        };
+    }
+    // Triple mustaches such as `{{{ body }}}` print unescaped HTML
+    let triple = inner
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+        .is_some_and(|name| name.trim().chars().all(|c| c.is_alphanumeric() || "_.".contains(c)));
+    if inner.starts_with(['^', '>', '!', '&']) || triple {
+        return Dialect::Mustache;
+    }
+
+    let word = first_word(inner);
+    let rest = inner[word.len()..].trim_start();
+    let go_argument = rest.starts_with(['.', '$', '"']) || (rest.is_empty() && word == "end");
+    if inner.starts_with(['.', '$']) || inner.starts_with("/*") || (GO_KEYWORDS.contains(&word.as_str()) && go_argument) {
+        return Dialect::GoTemplate;
+    }
+    Dialect::Jinja
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jinja::{self, Syntax};
+
+    fn detect(path: &str, content: &str, strategy: EscapeStrategy) -> Option<DialectReport> {
+        let syntax = Syntax::default();
+        syntax
+            .has_opener(content)
+            .then(|| DialectReport::detect(Path::new(path), content, &jinja::lex(content, &syntax), strategy))
+    }
+
+    fn dialects(path: &str, content: &str) -> Vec<Dialect> {
+        detect(path, content, EscapeStrategy::Auto)
+            .map(|report| report.dialects)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_detect_by_syntax() {
+        assert_eq!(dialects("ci.yml", "sha: ${{ github.sha }}"), [Dialect::GitHubActions]);
+        assert_eq!(
+            dialects("deployment.yaml", "{{- include \"app.labels\" . | nindent 4 }}\n{{- if .Values.image }}\nimage: {{ .Values.image }}\n{{ end }}"),
+            [Dialect::GoTemplate]
+        );
+        assert_eq!(dialects("list.html", "{{#each items}}{{name}}{{/each}}{{> footer}}"), [Dialect::Handlebars]);
+        assert_eq!(dialects("README.md", "{{ project-name }} and {{ item.id }}"), [Dialect::Jinja]);
+        assert_eq!(dialects("list.html", "{{#items}}{{name}}{{/items}}{{^items}}none{{/items}}"), [Dialect::Mustache]);
+        assert_eq!(dialects("page.html", "{{{ body }}}"), [Dialect::Mustache]);
+        assert_eq!(dialects("page.html", "{{ {'a': 1} }}"), [Dialect::Jinja]);
+        assert_eq!(
+            dialects("mixed.txt", "{% if ok %}{{ name | upper }}{% endif %} ${{ env.X }}"),
+            [Dialect::Jinja, Dialect::GitHubActions]
+        );
+        assert_eq!(dialects("plain.txt", "nothing here"), []);
+    }
+
+    #[test]
+    fn test_detect_by_location() {
+        assert_eq!(dialects(".github/workflows/ci.yml", "name: {{ x }}"), [Dialect::Jinja, Dialect::GitHubActions]);
+        assert_eq!(dialects("templates/_helpers.tpl", "{{ x }}"), [Dialect::Jinja, Dialect::GoTemplate]);
+        assert_eq!(dialects("views/page.hbs", "{{ title }}"), [Dialect::Handlebars]);
+        assert_eq!(dialects("views/page.hbs", "no tags"), []);
+    }
+
+    #[test]
+    fn test_escape_plan() {
+        let plan = |path: &str, content: &str, strategy| {
+            detect(path, content, strategy).unwrap().plan
+        };
+        assert_eq!(plan("a.yaml", "{{ .Values.x }}", EscapeStrategy::Auto), EscapePlan::WholeFile);
+        assert_eq!(plan("ci.yml", "${{ x }}", EscapeStrategy::Auto), EscapePlan::Strategy(EscapeStrategy::Raw));
+        assert_eq!(plan("a.txt", "{{ x }}", EscapeStrategy::Auto), EscapePlan::Strategy(EscapeStrategy::Auto));
+        assert_eq!(plan("a.txt.j2", "{{ x }}", EscapeStrategy::Raw), EscapePlan::WholeFile);
+        assert_eq!(plan("a.yaml", "{{ .Values.x }}", EscapeStrategy::Inline), EscapePlan::Strategy(EscapeStrategy::Inline));
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+use crate::templater::TemplateOptions;
+
+/// Glob filters deciding which entries a walk touches, and how.
+///
+/// Globs are matched against paths relative to the walk target using `/` separators.
+/// A glob without a `/` matches the entry's file name at any depth, like `.gitignore`.
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    content_scope: Option<GlobSet>,
+    path_scope: Option<GlobSet>,
+}
+
+impl PathFilter {
+    pub fn new(options: &TemplateOptions) -> Result<Self> {
+        Ok(Self {
+            include: build_glob_set(&options.include)?,
+            exclude: build_glob_set(&options.exclude)?,
+            content_scope: build_glob_set(&options.content_scope)?,
+            path_scope: build_glob_set(&options.path_scope)?,
+        })
+    }
+
+    /// Whether an entry should be left out of the walk entirely.
+    ///
+    /// Excluded directories are not descended into. Include globs only restrict files, so
+    /// directories are always walked to find included files.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if matches(&self.exclude, path) {
+            return true;
+        }
+        !is_dir && self.include.is_some() && !matches(&self.include, path)
+    }
+
+    /// Whether include globs restrict the walk to a subset of files.
+    pub fn restricts_files(&self) -> bool {
+        self.include.is_some()
+    }
+
+    /// Whether the contents of the file at `path` may be templated.
+    pub fn allows_contents(&self, path: &Path) -> bool {
+        self.content_scope.is_none() || matches(&self.content_scope, path)
+    }
+
+    /// Whether the entry at `path` may be renamed or moved.
+    pub fn allows_paths(&self, path: &Path) -> bool {
+        self.path_scope.is_none() || matches(&self.path_scope, path)
+    }
+}
+
+fn build_glob_set(globs: &[String]) -> Result<Option<GlobSet>> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let pattern = if glob.contains('/') {
+            glob.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", glob)
+        };
+        let glob = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid glob: {}", glob))?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
+}
+
+fn matches(set: &Option<GlobSet>, path: &Path) -> bool {
+    set.as_ref().is_some_and(|set| set.is_match(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str], content_scope: &[&str], path_scope: &[&str]) -> PathFilter {
+        let to_vec = |globs: &[&str]| globs.iter().map(|g| g.to_string()).collect();
+        PathFilter::new(&TemplateOptions {
+            include: to_vec(include),
+            exclude: to_vec(exclude),
+            content_scope: to_vec(content_scope),
+            path_scope: to_vec(path_scope),
+            ..TemplateOptions::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_exclude_matches_names_at_any_depth() {
+        let filter = filter(&[], &["node_modules", "*.lock"], &[], &[]);
+
+        assert!(filter.is_excluded(Path::new("web/node_modules"), true));
+        assert!(filter.is_excluded(Path::new("Cargo.lock"), false));
+        assert!(!filter.is_excluded(Path::new("src/main.rs"), false));
+    }
+
+    #[test]
+    fn test_include_only_restricts_files() {
+        let filter = filter(&["src/**/*.rs"], &[], &[], &[]);
+
+        assert!(!filter.is_excluded(Path::new("docs"), true));
+        assert!(!filter.is_excluded(Path::new("src/lib.rs"), false));
+        assert!(filter.is_excluded(Path::new("README.md"), false));
+        assert!(filter.is_excluded(Path::new("lib.rs"), false));
+    }
+
+    #[test]
+    fn test_scopes_default_to_everything() {
+        let unscoped = filter(&[], &[], &[], &[]);
+        assert!(unscoped.allows_contents(Path::new("a/b.txt")));
+        assert!(unscoped.allows_paths(Path::new("a/b.txt")));
+
+        let scoped = filter(&[], &[], &["**/*.java"], &["src/**"]);
+        assert!(scoped.allows_contents(Path::new("src/main/App.java")));
+        assert!(!scoped.allows_contents(Path::new("build.gradle")));
+        assert!(scoped.allows_paths(Path::new("src/main")));
+        assert!(!scoped.allows_paths(Path::new("src")));
+        assert!(!scoped.allows_paths(Path::new("test/App.java")));
+    }
+
+    #[test]
+    fn test_invalid_glob_is_reported() {
+        let options = TemplateOptions {
+            exclude: vec!["src/[".to_string()],
+            ..TemplateOptions::default()
+        };
+        assert!(PathFilter::new(&options).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Words whose plural is the same as their singular.
+const UNCOUNTABLE: &[&str] = &[
+    "data", "deer", "equipment", "feedback", "fish", "information", "metadata", "money", "news", "rice",
+    "series", "sheep", "software", "hardware", "species",
+];
+
+/// Singular and plural pairs that no suffix rule covers.
+const IRREGULAR: &[(&str, &str)] = &[
+    ("alias", "aliases"),
+    ("bonus", "bonuses"),
+    ("bus", "buses"),
+    ("cache", "caches"),
+    ("campus", "campuses"),
+    ("child", "children"),
+    ("cookie", "cookies"),
+    ("criterion", "criteria"),
+    ("foot", "feet"),
+    ("goose", "geese"),
+    ("index", "indices"),
+    ("man", "men"),
+    ("matrix", "matrices"),
+    ("mouse", "mice"),
+    ("movie", "movies"),
+    ("ox", "oxen"),
+    ("person", "people"),
+    ("quiz", "quizzes"),
+    ("status", "statuses"),
+    ("tooth", "teeth"),
+    ("vertex", "vertices"),
+    ("virus", "viruses"),
+    ("woman", "women"),
+    ("zombie", "zombies"),
+];
+
+/// Words ending in `f` or `fe` that take `ves` in the plural.
+const F_TO_VES: &[(&str, &str)] = &[
+    ("calf", "calves"),
+    ("elf", "elves"),
+    ("half", "halves"),
+    ("knife", "knives"),
+    ("leaf", "leaves"),
+    ("life", "lives"),
+    ("loaf", "loaves"),
+    ("self", "selves"),
+    ("shelf", "shelves"),
+    ("thief", "thieves"),
+    ("wife", "wives"),
+    ("wolf", "wolves"),
+];
+
+/// Words ending in `o` that take `es` in the plural; the rest just take `s`.
+const O_TO_OES: &[&str] = &["echo", "hero", "potato", "tomato", "torpedo", "veto"];
+
+/// Grammatical number a word can be turned into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Inflection {
+    Plural,
+    Singular,
+}
+
+impl Inflection {
+    /// The name used for this inflection, as in `--filter-name plural=pluralize`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Inflection::Plural => "plural",
+            Inflection::Singular => "singular",
+        }
+    }
+
+    /// The Jinja filter that inflects a variable this way, following Archetect's names.
+    pub fn filter(self) -> &'static str {
+        match self {
+            Inflection::Plural => "pluralize",
+            Inflection::Singular => "singularize",
+        }
+    }
+
+    /// Inflects a single lowercase word.
+    pub fn apply(self, word: &str) -> String {
+        match self {
+            Inflection::Plural => pluralize(word),
+            Inflection::Singular => singularize(word),
+        }
+    }
+
+    /// The inflection that turns the last of the lowercase `words` into its other number, or
+    /// `None` if both numbers are written the same.
+    pub fn opposite_of<S: AsRef<str>>(words: &[S]) -> Option<Self> {
+        let last = words.last()?.as_ref();
+        if singularize(last) != last {
+            Some(Inflection::Singular)
+        } else if pluralize(last) != last {
+            Some(Inflection::Plural)
+        } else {
+            None
+        }
+    }
+
+    /// Inflects the last of the lowercase `words`.
+    pub fn apply_to_last_word<S: AsRef<str>>(self, words: &[S]) -> Vec<String> {
+        let mut words: Vec<String> = words.iter().map(|word| word.as_ref().to_string()).collect();
+        if let Some(last) = words.last_mut() {
+            *last = self.apply(last);
+        }
+        words
+    }
+}
+
+impl fmt::Display for Inflection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The English plural of a lowercase word.
+pub fn pluralize(word: &str) -> String {
+    if word.is_empty() || UNCOUNTABLE.contains(&word) {
+        return word.to_string();
+    }
+    if let Some((_, plural)) = IRREGULAR.iter().chain(F_TO_VES).find(|(singular, _)| *singular == word) {
+        return plural.to_string();
+    }
+    if IRREGULAR.iter().chain(F_TO_VES).any(|(_, plural)| *plural == word) {
+        return word.to_string();
+    }
+
+    if let Some(stem) = word.strip_suffix("sis") {
+        format!("{}ses", stem)
+    } else if let Some(stem) = word.strip_suffix('y').filter(|stem| ends_with_consonant(stem)) {
+        format!("{}ies", stem)
+    } else if ["s", "x", "z", "ch", "sh"].iter().any(|suffix| word.ends_with(suffix)) || O_TO_OES.contains(&word) {
+        format!("{}es", word)
+    } else {
+        format!("{}s", word)
+    }
+}
+
+/// The English singular of a lowercase word; words that are already singular are returned as is.
+pub fn singularize(word: &str) -> String {
+    if word.is_empty() || UNCOUNTABLE.contains(&word) {
+        return word.to_string();
+    }
+    if let Some((singular, _)) = IRREGULAR.iter().chain(F_TO_VES).find(|(_, plural)| *plural == word) {
+        return singular.to_string();
+    }
+    if IRREGULAR.iter().chain(F_TO_VES).any(|(singular, _)| *singular == word) {
+        return word.to_string();
+    }
+    if let Some(hero) = O_TO_OES.iter().find(|hero| word.strip_suffix("es") == Some(**hero)) {
+        return hero.to_string();
+    }
+
+    if let Some(stem) = word.strip_suffix("yses") {
+        format!("{}ysis", stem)
+    } else if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.len() > 1) {
+        format!("{}y", stem)
+    } else if let Some(stem) = ["sses", "xes", "zzes", "ches", "shes"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix).map(|stem| (stem, &suffix[..suffix.len() - 2])))
+        .map(|(stem, kept)| format!("{}{}", stem, kept))
+    {
+        stem
+    } else if word.ends_with('s') && !["ss", "us", "is"].iter().any(|suffix| word.ends_with(suffix)) {
+        word[..word.len() - 1].to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+fn ends_with_consonant(stem: &str) -> bool {
+    stem.chars().next_back().is_some_and(|c| c.is_alphabetic() && !"aeiou".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIRS: &[(&str, &str)] = &[
+        ("widget", "widgets"),
+        ("category", "categories"),
+        ("key", "keys"),
+        ("box", "boxes"),
+        ("branch", "branches"),
+        ("address", "addresses"),
+        ("analysis", "analyses"),
+        ("hero", "heroes"),
+        ("photo", "photos"),
+        ("knife", "knives"),
+        ("archive", "archives"),
+        ("person", "people"),
+        ("status", "statuses"),
+        ("database", "databases"),
+        ("news", "news"),
+    ];
+
+    #[test]
+    fn test_pluralize_and_singularize() {
+        for (singular, plural) in PAIRS {
+            assert_eq!(pluralize(singular), *plural, "plural of {}", singular);
+            assert_eq!(singularize(plural), *singular, "singular of {}", plural);
+            assert_eq!(singularize(singular), *singular, "{} is already singular", singular);
+        }
+    }
+
+    #[test]
+    fn test_inflect_last_word() {
+        assert_eq!(Inflection::opposite_of(&["order", "item"]), Some(Inflection::Plural));
+        assert_eq!(Inflection::opposite_of(&["order", "items"]), Some(Inflection::Singular));
+        assert_eq!(Inflection::opposite_of(&["breaking", "news"]), None);
+        assert_eq!(Inflection::Plural.apply_to_last_word(&["order", "category"]), ["order", "categories"]);
+    }
+}
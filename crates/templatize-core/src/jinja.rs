@@ -0,0 +1,948 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// [`EscapeStrategy::Auto`] escapes a file with raw blocks once it holds at least this many tags...
+const AUTO_RAW_MIN_TAGS: usize = 4;
+
+/// ...on at least this fraction of its non-blank lines.
+const AUTO_RAW_MIN_DENSITY: f64 = 0.25;
+
+/// The kinds of tag, each opened by its own delimiter.
+const TAG_KINDS: [SegmentKind; 3] = [SegmentKind::Expression, SegmentKind::Statement, SegmentKind::Comment];
+
+/// How [`escape_with`] writes escaped template syntax.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EscapeStrategy {
+    /// Escape each opening delimiter in place, as in `{{'{'}}{ name }}`.
+    Inline,
+    /// Wrap each run of lines holding template syntax in `{% raw %}...{% endraw %}`.
+    Raw,
+    /// Use raw blocks for files dense with template syntax and inline escapes for the rest.
+    #[default]
+    Auto,
+}
+
+/// The delimiters a template is written with, such as `[[ name ]]` and `[% if x %]` instead of
+/// the standard `{{ name }}` and `{% if x %}`.
+///
+/// Parsed from the variable, block and (optionally) comment delimiters separated by spaces, as
+/// in `"[[ ]] [% %] [# #]"`. Without comment delimiters, they are the block delimiters with `%`
+/// replaced by `#`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Syntax {
+    pub variable: (String, String),
+    pub block: (String, String),
+    pub comment: (String, String),
+}
+
+impl Default for Syntax {
+    fn default() -> Self {
+        Self {
+            variable: ("{{".into(), "}}".into()),
+            block: ("{%".into(), "%}".into()),
+            comment: ("{#".into(), "#}".into()),
+        }
+    }
+}
+
+impl Syntax {
+    /// Whether these are the standard `{{ }}`, `{% %}` and `{# #}` delimiters.
+    pub fn is_standard(&self) -> bool {
+        *self == Syntax::default()
+    }
+
+    /// Rewrites the tags of `text`, written in this syntax, into `to`.
+    pub fn translate(&self, text: &str, to: &Syntax) -> String {
+        if self == to {
+            return text.to_string();
+        }
+        let mut translated = String::with_capacity(text.len());
+        for segment in lex(text, self) {
+            let source = &text[segment.range.clone()];
+            let (from, into) = match segment.kind {
+                SegmentKind::Text => {
+                    translated.push_str(source);
+                    continue;
+                }
+                SegmentKind::Raw => {
+                    translated.push_str(&self.translate(&text[segment.range.start..segment.inner.start], to));
+                    translated.push_str(segment.inner(text));
+                    translated.push_str(&self.translate(&text[segment.inner.end..segment.range.end], to));
+                    continue;
+                }
+                kind => (self.delimiters(kind), to.delimiters(kind)),
+            };
+            translated.push_str(&into.0);
+            translated.push_str(&source[from.0.len()..source.len() - from.1.len()]);
+            translated.push_str(&into.1);
+        }
+        translated
+    }
+
+    /// Rewrites the tags of `text` written in either the standard syntax or this one into this
+    /// one, so replacements can be given in whichever the user prefers.
+    pub fn adopt(&self, text: &str) -> String {
+        let standard = Syntax::default();
+        standard.translate(&self.translate(text, &standard), self)
+    }
+
+    /// Renderer settings that select this syntax, for minijinja and Jinja2.
+    pub fn renderer_config(&self) -> String {
+        let (variable, block, comment) = (&self.variable, &self.block, &self.comment);
+        format!(
+            "minijinja:\n    \
+             env.set_syntax(\n        \
+             SyntaxConfig::builder()\n            \
+             .variable_delimiters({:?}, {:?})\n            \
+             .block_delimiters({:?}, {:?})\n            \
+             .comment_delimiters({:?}, {:?})\n            \
+             .build()?,\n    \
+             );\n\
+             Jinja2:\n    \
+             Environment(\n        \
+             variable_start_string={:?}, variable_end_string={:?},\n        \
+             block_start_string={:?}, block_end_string={:?},\n        \
+             comment_start_string={:?}, comment_end_string={:?},\n    \
+             )",
+            variable.0, variable.1, block.0, block.1, comment.0, comment.1,
+            variable.0, variable.1, block.0, block.1, comment.0, comment.1,
+        )
+    }
+
+    fn delimiters(&self, kind: SegmentKind) -> &(String, String) {
+        match kind {
+            SegmentKind::Expression => &self.variable,
+            SegmentKind::Comment => &self.comment,
+            _ => &self.block,
+        }
+    }
+
+    /// The kind of tag whose opening delimiter starts at `at`, preferring the longest delimiter.
+    fn opener_at(&self, source: &str, at: usize) -> Option<SegmentKind> {
+        self.opener_continuing(source, at, "")
+    }
+
+    /// The kind of tag whose opening delimiter is `printed` followed by the text at `at`,
+    /// preferring the longest delimiter.
+    fn opener_continuing(&self, source: &str, at: usize, printed: &str) -> Option<SegmentKind> {
+        let rest = &source[at..];
+        TAG_KINDS
+            .into_iter()
+            .filter(|&kind| {
+                let open = &self.delimiters(kind).0;
+                open.strip_prefix(printed).is_some_and(|tail| rest.starts_with(tail))
+            })
+            .max_by_key(|&kind| self.delimiters(kind).0.len())
+    }
+
+    /// Whether `source` holds any opening delimiter, which is all that needs escaping; found
+    /// without lexing.
+    pub fn has_opener(&self, source: &str) -> bool {
+        TAG_KINDS.into_iter().any(|kind| source.contains(self.delimiters(kind).0.as_str()))
+    }
+
+    /// An expression printing `c`, which escapes the opening delimiter `c` starts.
+    fn escaped(&self, c: char) -> String {
+        let quote = if c == '\'' { '"' } else { '\'' };
+        format!("{}{quote}{}{quote}{}", self.variable.0, c, self.variable.1)
+    }
+
+    fn raw_start(&self) -> String {
+        format!("{} raw {}", self.block.0, self.block.1)
+    }
+
+    fn raw_end(&self) -> String {
+        format!("{} endraw {}", self.block.0, self.block.1)
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let delimiters: Vec<&str> = text.split_whitespace().collect();
+        let pair = |i: usize| (delimiters[i].to_string(), delimiters[i + 1].to_string());
+        let syntax = match delimiters.len() {
+            4 => {
+                let block = pair(2);
+                let comment = (block.0.replace('%', "#"), block.1.replace('%', "#"));
+                if comment == block {
+                    return Err(format!(
+                        "Can't derive comment delimiters from '{} {}'; give them after the block delimiters",
+                        block.0, block.1
+                    ));
+                }
+                Syntax {
+                    variable: pair(0),
+                    block,
+                    comment,
+                }
+            }
+            6 => Syntax {
+                variable: pair(0),
+                block: pair(2),
+                comment: pair(4),
+            },
+            _ => {
+                return Err(format!(
+                    "Expected variable, block and optional comment delimiters such as '[[ ]] [% %]', got '{}'",
+                    text
+                ))
+            }
+        };
+        let starts = [&syntax.variable.0, &syntax.block.0, &syntax.comment.0];
+        if starts.iter().enumerate().any(|(i, start)| starts[..i].contains(start)) {
+            return Err(format!("The opening delimiters in '{}' must differ", text));
+        }
+        Ok(syntax)
+    }
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.variable.0, self.variable.1, self.block.0, self.block.1, self.comment.0, self.comment.1
+        )
+    }
+}
+
+/// What a piece of a Jinja source is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SegmentKind {
+    /// Literal text outside any tag.
+    Text,
+    /// An expression such as `{{ name | upper }}`.
+    Expression,
+    /// A statement such as `{% if x %}`.
+    Statement,
+    /// A comment such as `{# note #}`.
+    Comment,
+    /// A `{% raw %}` block through its `{% endraw %}`, tags included.
+    Raw,
+}
+
+/// A byte range of a Jinja source and what it holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub range: Range<usize>,
+    /// The text between the delimiters of a tag, without whitespace-control marks; the body of
+    /// a raw block; or all of a text segment.
+    pub inner: Range<usize>,
+}
+
+impl Segment {
+    /// The text of [`Segment::inner`].
+    pub fn inner<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.inner.clone()]
+    }
+}
+
+/// Splits `source`, written in `syntax`, into text, tags and raw blocks; the segments cover
+/// `source` exactly.
+///
+/// Closing delimiters inside string literals or nested brackets, as in `{{ {'a': '}}'} }}`,
+/// don't end a tag. An opening delimiter that is never closed is left as text.
+pub fn lex(source: &str, syntax: &Syntax) -> Vec<Segment> {
+    Scanner::new(source, syntax).lex()
+}
+
+/// Finds the tags of a source left to right, remembering where each opening delimiter next
+/// occurs and where each closing delimiter last occurs, so that no stretch of the source is
+/// searched for the same delimiter twice and tags that can never close are given up at once.
+///
+/// Where string literals, bracket groups and the code of tags end is remembered too, so a tag
+/// that starts inside code already walked, as unclosed and escaped openers do, costs no more
+/// than the stretch that is new to it.
+struct Scanner<'a> {
+    source: &'a str,
+    syntax: &'a Syntax,
+    /// Where the opening delimiter of each of [`TAG_KINDS`] next occurs, as of the last search.
+    next_open: [Option<usize>; 3],
+    /// Where the last closing delimiter of each of [`TAG_KINDS`] starts.
+    last_close: [Option<usize>; 3],
+    /// A position after which there is no `{% endraw %}`.
+    no_endraw_after: usize,
+    /// The closing quote of the string literal opening at each position.
+    string_ends: HashMap<usize, Option<usize>>,
+    /// The closing bracket of the group opening at each position.
+    group_ends: HashMap<usize, Option<usize>>,
+    /// Where the closing delimiter of a kind of tag ends the code starting at a position.
+    code_ends: HashMap<(SegmentKind, usize), Option<usize>>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(source: &'a str, syntax: &'a Syntax) -> Self {
+        Self {
+            source,
+            syntax,
+            next_open: TAG_KINDS.map(|kind| source.find(syntax.delimiters(kind).0.as_str())),
+            last_close: TAG_KINDS.map(|kind| source.rfind(syntax.delimiters(kind).1.as_str())),
+            no_endraw_after: usize::MAX,
+            string_ends: HashMap::new(),
+            group_ends: HashMap::new(),
+            code_ends: HashMap::new(),
+        }
+    }
+
+    fn lex(&mut self) -> Vec<Segment> {
+        let source = self.source;
+        let mut segments = Vec::new();
+        let mut text_start = 0;
+        let mut from = 0;
+        while let Some(start) = self.next_opener(from) {
+            let Some(mut segment) = self.tag_at(start) else {
+                from = start + source[start..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            if segment.kind == SegmentKind::Statement && segment.inner(source).trim() == "raw" {
+                if let Some((body_end, raw_end)) = self.endraw_after(segment.range.end) {
+                    segment = Segment {
+                        kind: SegmentKind::Raw,
+                        range: start..raw_end,
+                        inner: segment.range.end..body_end,
+                    };
+                }
+            }
+
+            if text_start < start {
+                segments.push(text_segment(text_start..start));
+            }
+            from = segment.range.end;
+            text_start = from;
+            segments.push(segment);
+        }
+        if text_start < source.len() {
+            segments.push(text_segment(text_start..source.len()));
+        }
+        segments
+    }
+
+    /// The first position at or after `from` where an opening delimiter starts; `from` must
+    /// not decrease between calls.
+    fn next_opener(&mut self, from: usize) -> Option<usize> {
+        for (next, kind) in self.next_open.iter_mut().zip(TAG_KINDS) {
+            if next.is_some_and(|at| at < from) {
+                let open = &self.syntax.delimiters(kind).0;
+                *next = self.source[from..].find(open.as_str()).map(|offset| from + offset);
+            }
+        }
+        self.next_open.iter().flatten().min().copied()
+    }
+
+    /// The tag opening at `start`, if the source has one there.
+    fn tag_at(&mut self, start: usize) -> Option<Segment> {
+        let kind = self.syntax.opener_at(self.source, start)?;
+        self.tag_from(start, kind, start + self.syntax.delimiters(kind).0.len())
+    }
+
+    /// Where the tag ends that `printed`, written out by an escape just before `start`, opens
+    /// together with the text at `start`.
+    fn escaped_tag_end(&mut self, start: usize, printed: &str) -> Option<usize> {
+        let kind = self.syntax.opener_continuing(self.source, start, printed)?;
+        let body_start = start + self.syntax.delimiters(kind).0.len() - printed.len();
+        self.tag_from(start, kind, body_start).map(|tag| tag.range.end)
+    }
+
+    /// The tag of `kind` opening at `start`, whose body starts at `body_start`.
+    fn tag_from(&mut self, start: usize, kind: SegmentKind, body_start: usize) -> Option<Segment> {
+        let source = self.source;
+        let close = &self.syntax.delimiters(kind).1;
+        let last_close = TAG_KINDS.iter().position(|&k| k == kind).and_then(|i| self.last_close[i]);
+        // A tag opened after the last closing delimiter never closes
+        if last_close.is_none_or(|at| at < body_start) {
+            return None;
+        }
+        let close_start = if kind == SegmentKind::Comment {
+            body_start + source[body_start..].find(close.as_str())?
+        } else {
+            self.code_end(body_start, kind)?
+        };
+
+        // Whitespace-control marks belong to the delimiters
+        let body = &source[body_start..close_start];
+        let inner_start = body_start + usize::from(body.starts_with(['-', '+']));
+        let inner_end = (close_start - usize::from(body.ends_with(['-', '+']))).max(inner_start);
+        Some(Segment {
+            kind,
+            range: start..close_start + close.len(),
+            inner: inner_start..inner_end,
+        })
+    }
+
+    /// Where the closing delimiter of `kind` ends the code starting at `from`, skipping string
+    /// literals and bracket groups.
+    fn code_end(&mut self, from: usize, kind: SegmentKind) -> Option<usize> {
+        let source = self.source;
+        let (open, close) = self.syntax.delimiters(kind);
+        // Remember the result for every position a later tag of this kind could start its
+        // code at, since the code from there ends in the same place
+        let body_start = |at: usize| at == from || open.chars().next_back().is_some_and(|c| source[..at].ends_with(c));
+        let mut visited = Vec::new();
+        let mut at = from;
+        let end = loop {
+            if let Some(&end) = self.code_ends.get(&(kind, at)) {
+                break end;
+            }
+            if body_start(at) {
+                visited.push(at);
+            }
+            if source[at..].starts_with(close.as_str()) {
+                break Some(at);
+            }
+            let Some(c) = source[at..].chars().next() else {
+                break None;
+            };
+            let skipped = match c {
+                '\'' | '"' => self.string_end(at),
+                '{' | '[' | '(' => self.group_end(at),
+                // Stray closing brackets are ignored
+                _ => Some(at + c.len_utf8() - 1),
+            };
+            match skipped {
+                Some(last) => at = last + 1,
+                None => break None,
+            }
+        };
+        for position in visited {
+            self.code_ends.insert((kind, position), end);
+        }
+        end
+    }
+
+    /// The closing quote of the string literal whose opening quote is at `quote_at`.
+    fn string_end(&mut self, quote_at: usize) -> Option<usize> {
+        let source = self.source;
+        *self.string_ends.entry(quote_at).or_insert_with(|| {
+            let quote = source[quote_at..].chars().next()?;
+            let mut chars = source[quote_at + 1..].char_indices();
+            while let Some((offset, c)) = chars.next() {
+                if c == '\\' {
+                    chars.next();
+                } else if c == quote {
+                    return Some(quote_at + 1 + offset);
+                }
+            }
+            None
+        })
+    }
+
+    /// The closing bracket of the group whose opening bracket is at `open_at`, skipping string
+    /// literals and nested groups; any closing bracket closes the innermost group.
+    fn group_end(&mut self, open_at: usize) -> Option<usize> {
+        if let Some(&end) = self.group_ends.get(&open_at) {
+            return end;
+        }
+        let source = self.source;
+        let mut open = vec![open_at];
+        let mut at = open_at + 1;
+        while let Some(c) = source[at..].chars().next() {
+            let nested = match c {
+                '\'' | '"' => self.string_end(at),
+                '{' | '[' | '(' => match self.group_ends.get(&at) {
+                    Some(&end) => end,
+                    None => {
+                        open.push(at);
+                        at += 1;
+                        continue;
+                    }
+                },
+                '}' | ']' | ')' => {
+                    let group = open.pop().expect("a group is open until its end is found");
+                    self.group_ends.insert(group, Some(at));
+                    if open.is_empty() {
+                        return Some(at);
+                    }
+                    Some(at)
+                }
+                _ => Some(at + c.len_utf8() - 1),
+            };
+            match nested {
+                Some(last) => at = last + 1,
+                None => break,
+            }
+        }
+        // Whatever is still open never closes
+        for group in open {
+            self.group_ends.insert(group, None);
+        }
+        None
+    }
+
+    /// The first `{% endraw %}` tag at or after `from`, as [`find_endraw`] finds it.
+    fn endraw_after(&mut self, from: usize) -> Option<(usize, usize)> {
+        if from >= self.no_endraw_after {
+            return None;
+        }
+        let found = find_endraw(self.source, from, self.syntax);
+        if found.is_none() {
+            self.no_endraw_after = from;
+        }
+        found
+    }
+}
+
+fn text_segment(range: Range<usize>) -> Segment {
+    Segment {
+        kind: SegmentKind::Text,
+        inner: range.clone(),
+        range,
+    }
+}
+
+/// Byte ranges of `source` that hold template syntax: tags, raw blocks, and text escaped by a
+/// preceding expression that only prints an opening delimiter, as in `{{'{'}}{ name }}`.
+pub fn protected_ranges(source: &str, syntax: &Syntax) -> Vec<Range<usize>> {
+    let mut scanner = Scanner::new(source, syntax);
+    let segments = scanner.lex();
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        match segment.kind {
+            SegmentKind::Text => {
+                let printed = i
+                    .checked_sub(1)
+                    .and_then(|prev| printed_open_delimiter(&segments[prev], source, syntax));
+                let Some(printed) = printed else {
+                    continue;
+                };
+                // The escaped tag ends where it would if the printed delimiter were written out
+                let start = segment.range.start;
+                if let Some(end) = scanner.escaped_tag_end(start, printed) {
+                    extend(&mut ranges, start..end);
+                }
+            }
+            _ => extend(&mut ranges, segment.range.clone()),
+        }
+    }
+    ranges
+}
+
+/// Applies `replace` to each stretch of `source` outside [`protected_ranges`], returning
+/// `None` if no stretch changed; `protected` sees the text of each range left alone.
+pub fn replace_unprotected<F, P>(source: &str, syntax: &Syntax, mut replace: F, mut protected: P) -> Option<String>
+where
+    F: FnMut(&str) -> Option<String>,
+    P: FnMut(&str),
+{
+    let mut result = String::with_capacity(source.len());
+    let mut changed = false;
+    let mut last = 0;
+    let ranges = protected_ranges(source, syntax);
+    for range in ranges.into_iter().chain(std::iter::once(source.len()..source.len())) {
+        let text = &source[last..range.start];
+        match replace(text) {
+            Some(new_text) => {
+                changed = true;
+                result.push_str(&new_text);
+            }
+            None => result.push_str(text),
+        }
+        protected(&source[range.clone()]);
+        result.push_str(&source[range.clone()]);
+        last = range.end;
+    }
+    changed.then_some(result)
+}
+
+/// Whether `token` occurs in `replacement` outside its template syntax, so that running the
+/// same replacement again would replace it again.
+pub fn reintroduces(token: &str, replacement: &str, syntax: &Syntax) -> bool {
+    let mut found = false;
+    replace_unprotected(
+        replacement,
+        syntax,
+        |text| {
+            found |= !token.is_empty() && text.contains(token);
+            None
+        },
+        |_| {},
+    );
+    found
+}
+
+/// Escapes `source` so that rendering the result with `syntax` reproduces it byte for byte,
+/// returning `None` if it holds no opening delimiters.
+///
+/// The first character of every opening delimiter is written as an expression printing it, as
+/// in `{{'{'}}{ name }}`. That covers tags, whitespace-control forms, raw blocks, delimiters
+/// inside string literals and openers that are never closed, and leaves everything else,
+/// whitespace included, exactly as it was.
+pub fn escape(source: &str, syntax: &Syntax) -> Option<String> {
+    let mut escaped = String::with_capacity(source.len());
+    let mut changed = false;
+    for (at, c) in source.char_indices() {
+        if syntax.opener_at(source, at).is_some() {
+            escaped.push_str(&syntax.escaped(c));
+            changed = true;
+        } else {
+            escaped.push(c);
+        }
+    }
+    changed.then_some(escaped)
+}
+
+/// Escapes `source` with `strategy`, returning `None` if it holds no opening delimiters.
+pub fn escape_with(source: &str, strategy: EscapeStrategy, syntax: &Syntax) -> Option<String> {
+    match strategy {
+        EscapeStrategy::Inline => escape(source, syntax),
+        _ => escape_lexed(source, &lex(source, syntax), strategy, syntax),
+    }
+}
+
+/// [`escape_with`] for a `source` already split into `segments` by [`lex`].
+pub fn escape_lexed(source: &str, segments: &[Segment], strategy: EscapeStrategy, syntax: &Syntax) -> Option<String> {
+    let regions = syntax_regions(source, segments, syntax);
+    if regions.is_empty() {
+        return None;
+    }
+    let use_raw = match strategy {
+        EscapeStrategy::Inline => false,
+        EscapeStrategy::Raw => true,
+        EscapeStrategy::Auto => is_dense(source, segments, &regions),
+    };
+    if !use_raw {
+        return escape(source, syntax);
+    }
+
+    let mut escaped = String::with_capacity(source.len());
+    let mut last = 0;
+    for region in regions {
+        escaped.push_str(&source[last..region.start]);
+        escaped.push_str(&wrap_raw(&source[region.clone()], syntax));
+        last = region.end;
+    }
+    escaped.push_str(&source[last..]);
+    Some(escaped)
+}
+
+/// Wraps all of `source` in a raw block, returning `None` if it holds no opening delimiters.
+pub fn escape_raw(source: &str, syntax: &Syntax) -> Option<String> {
+    syntax.has_opener(source).then(|| wrap_raw(source, syntax))
+}
+
+/// Reverses [`escape`] and [`escape_with`], returning `None` if `source` holds no escapes.
+///
+/// Expressions that only print an opening delimiter, as in `{{'{'}}`, are replaced by what they
+/// print, and raw blocks by their bodies, which is how Jinja would render them. Tags with
+/// whitespace-control marks, which render differently, and all other syntax are kept as is.
+pub fn unescape(source: &str, syntax: &Syntax) -> Option<String> {
+    let mut unescaped = String::with_capacity(source.len());
+    let mut changed = false;
+    for segment in lex(source, syntax) {
+        let rendered = match segment.kind {
+            SegmentKind::Expression if !has_whitespace_control(&segment, syntax) => {
+                printed_open_delimiter(&segment, source, syntax)
+            }
+            SegmentKind::Raw if is_plain_raw_block(&segment, source, syntax) => Some(segment.inner(source)),
+            _ => None,
+        };
+        match rendered {
+            Some(text) => {
+                unescaped.push_str(text);
+                changed = true;
+            }
+            None => unescaped.push_str(&source[segment.range]),
+        }
+    }
+    changed.then_some(unescaped)
+}
+
+/// `text` inside `{% raw %}...{% endraw %}`, with any `{% endraw %}` tags it holds escaped
+/// inline between raw blocks so they don't end the block early.
+fn wrap_raw(text: &str, syntax: &Syntax) -> String {
+    let (raw_start, raw_end) = (syntax.raw_start(), syntax.raw_end());
+    let mut wrapped = raw_start.clone();
+    let mut last = 0;
+    while let Some((start, end)) = find_endraw(text, last, syntax) {
+        wrapped.push_str(&text[last..start]);
+        wrapped.push_str(&raw_end);
+        wrapped.push_str(&escape(&text[start..end], syntax).unwrap_or_default());
+        wrapped.push_str(&raw_start);
+        last = end;
+    }
+    wrapped.push_str(&text[last..]);
+    wrapped.push_str(&raw_end);
+    wrapped
+}
+
+/// Byte ranges of the runs of whole lines, without their final line break, that hold opening
+/// delimiters, including tags that span several lines.
+fn syntax_regions(source: &str, segments: &[Segment], syntax: &Syntax) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    for segment in segments {
+        if segment.kind != SegmentKind::Text {
+            spans.push(segment.range.clone());
+            continue;
+        }
+        // Openers that are never closed still need escaping
+        for (at, _) in source[segment.range.clone()].char_indices() {
+            let start = segment.range.start + at;
+            if syntax.opener_at(source, start).is_some() {
+                spans.push(start..start + 1);
+            }
+        }
+    }
+
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    for span in spans {
+        let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[span.end..].find('\n').map_or(source.len(), |i| span.end + i);
+        match regions.last_mut() {
+            // Syntax on the same or consecutive lines forms one run
+            Some(last) if line_start <= last.end + 1 => last.end = last.end.max(line_end),
+            _ => regions.push(line_start..line_end),
+        }
+    }
+    regions
+}
+
+/// Whether `source`, split into `segments` with its [`syntax_regions`], holds enough template
+/// syntax for [`EscapeStrategy::Auto`] to use raw blocks.
+fn is_dense(source: &str, segments: &[Segment], regions: &[Range<usize>]) -> bool {
+    let non_blank = |text: &str| text.lines().filter(|line| !line.trim().is_empty()).count();
+    let tags = segments
+        .iter()
+        .filter(|segment| segment.kind != SegmentKind::Text)
+        .count();
+    let syntax_lines: usize = regions
+        .iter()
+        .map(|region| non_blank(&source[region.clone()]))
+        .sum();
+    tags >= AUTO_RAW_MIN_TAGS && syntax_lines as f64 >= non_blank(source) as f64 * AUTO_RAW_MIN_DENSITY
+}
+
+/// Adds `range` to the sorted `ranges`, merging it into the last one if they touch or overlap.
+fn extend(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    match ranges.last_mut() {
+        Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+        _ => ranges.push(range),
+    }
+}
+
+/// Whether the tag `segment` has whitespace-control marks, as in `{{- name }}`.
+fn has_whitespace_control(segment: &Segment, syntax: &Syntax) -> bool {
+    let (open, close) = syntax.delimiters(segment.kind);
+    segment.inner.start != segment.range.start + open.len() || segment.inner.end != segment.range.end - close.len()
+}
+
+/// Whether the raw block `segment` opens with `{% raw %}` and closes with `{% endraw %}`, with
+/// no whitespace-control marks, so that it renders exactly as its body.
+fn is_plain_raw_block(segment: &Segment, source: &str, syntax: &Syntax) -> bool {
+    let (open, close) = &syntax.block;
+    let tag_is = |tag: &str, name: &str| {
+        tag.strip_prefix(open.as_str())
+            .and_then(|tag| tag.strip_suffix(close.as_str()))
+            .is_some_and(|body| body.trim() == name && !body.trim_start().starts_with(['-', '+']))
+    };
+    tag_is(&source[segment.range.start..segment.inner.start], "raw")
+        && tag_is(&source[segment.inner.end..segment.range.end], "endraw")
+}
+
+/// The start of an opening delimiter printed by `segment`, if it is an expression printing
+/// only such a string literal.
+fn printed_open_delimiter<'a>(segment: &Segment, source: &'a str, syntax: &Syntax) -> Option<&'a str> {
+    if segment.kind != SegmentKind::Expression {
+        return None;
+    }
+    let inner = segment.inner(source).trim();
+    let literal = inner
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+        .or_else(|| inner.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')))?;
+    let opens = [&syntax.variable.0, &syntax.block.0, &syntax.comment.0]
+        .iter()
+        .any(|open| open.starts_with(literal));
+    (!literal.is_empty() && opens).then_some(literal)
+}
+
+/// The start and end of the first `{% endraw %}` tag at or after `from`, with any
+/// whitespace-control marks.
+///
+/// Like Jinja, this only looks at the text, so a raw block ends at an `endraw` tag even inside
+/// what looks like a comment or a string.
+fn find_endraw(source: &str, from: usize, syntax: &Syntax) -> Option<(usize, usize)> {
+    let (open, close) = &syntax.block;
+    let mut search = from;
+    while let Some(offset) = source[search..].find(open.as_str()) {
+        let start = search + offset;
+        let rest = &source[start + open.len()..];
+        let rest = rest.strip_prefix(['-', '+']).unwrap_or(rest).trim_start();
+        if let Some(rest) = rest.strip_prefix("endraw") {
+            let rest = rest.trim_start();
+            let rest = rest.strip_prefix(['-', '+']).unwrap_or(rest);
+            if rest.starts_with(close.as_str()) {
+                return Some((start, source.len() - rest.len() + close.len()));
+            }
+        }
+        search = start + open.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(SegmentKind, &str)> {
+        lex(source, &Syntax::default())
+            .into_iter()
+            .map(|s| (s.kind, &source[s.range]))
+            .collect()
+    }
+
+    fn brackets() -> Syntax {
+        "[[ ]] [% %]".parse().unwrap()
+    }
+
+    #[test]
+    fn test_lex() {
+        use SegmentKind::*;
+        assert_eq!(
+            kinds("a {{ b }} {%- if c -%}{# d #}{{ {'e': '}}'} }}{ f"),
+            [
+                (Text, "a "),
+                (Expression, "{{ b }}"),
+                (Text, " "),
+                (Statement, "{%- if c -%}"),
+                (Comment, "{# d #}"),
+                (Expression, "{{ {'e': '}}'} }}"),
+                (Text, "{ f"),
+            ]
+        );
+        assert_eq!(
+            kinds("x {% raw %}{{ y }}{%- endraw %} z {{ open"),
+            [(Text, "x "), (Raw, "{% raw %}{{ y }}{%- endraw %}"), (Text, " z {{ open")]
+        );
+    }
+
+    #[test]
+    fn test_replace_unprotected() {
+        let source = "app {{ app }} {{'{'}}{ app }} {% if app %}app{% endif %} {% raw %}app{% endraw %}";
+        assert_eq!(
+            replace_unprotected(source, &Syntax::default(), |text| Some(text.replace("app", "X")), |_| {}).unwrap(),
+            "X {{ app }} {{'{'}}{ app }} {% if app %}X{% endif %} {% raw %}app{% endraw %}"
+        );
+    }
+
+    #[test]
+    fn test_escape_is_lossless() {
+        let syntax = Syntax::default();
+        assert_eq!(escape("no tags { here }", &syntax), None);
+        assert_eq!(
+            escape("{{  a  }} {%- if b -%}{# c #}", &syntax).unwrap(),
+            "{{'{'}}{  a  }} {{'{'}}%- if b -%}{{'{'}}# c #}"
+        );
+        assert_eq!(
+            escape("{{ {'a': {'b': '{%'}} }}", &syntax).unwrap(),
+            "{{'{'}}{ {'a': {'b': '{{'{'}}%'}} }}"
+        );
+        assert_eq!(escape("{{{ x }}}", &syntax).unwrap(), "{{'{'}}{{'{'}}{ x }}}");
+    }
+
+    #[test]
+    fn test_escape_with_raw_blocks() {
+        let syntax = Syntax::default();
+        let source = "name: app\nimage: {{ image }}\ntag: {{ tag }}\n\nports:\n  - 80\n{# {% endraw %} #}\n";
+        assert_eq!(
+            escape_with(source, EscapeStrategy::Raw, &syntax).unwrap(),
+            "name: app\n{% raw %}image: {{ image }}\ntag: {{ tag }}{% endraw %}\n\nports:\n  - 80\n\
+             {% raw %}{# {% endraw %}{{'{'}}% endraw %}{% raw %} #}{% endraw %}\n"
+        );
+        assert_eq!(escape_raw("a {{ b }}\n", &syntax).unwrap(), "{% raw %}a {{ b }}\n{% endraw %}");
+        assert_eq!(escape_with("no syntax", EscapeStrategy::Raw, &syntax), None);
+        assert_eq!(escape_raw("no syntax", &syntax), None);
+        assert!(syntax.has_opener("unclosed {# note") && !syntax.has_opener("{ } %} }}"));
+    }
+
+    #[test]
+    fn test_auto_escape_chooses_by_density() {
+        let syntax = Syntax::default();
+        let sparse = "one {{ a }}\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n";
+        assert_eq!(escape_with(sparse, EscapeStrategy::Auto, &syntax), escape(sparse, &syntax));
+
+        let dense = "{% for x in xs %}\n{{ x.a }}\n{{ x.b }}\n{% endfor %}\nend\n";
+        assert_eq!(
+            escape_with(dense, EscapeStrategy::Auto, &syntax).unwrap(),
+            "{% raw %}{% for x in xs %}\n{{ x.a }}\n{{ x.b }}\n{% endfor %}{% endraw %}\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_unescape_reverses_escape() {
+        let syntax = Syntax::default();
+        let source = "name: app\nimage: {{ image }}\n{%- if x %}{# {% endraw %} #}\n{{{ y }}} {{ open\n";
+        for strategy in [EscapeStrategy::Inline, EscapeStrategy::Raw, EscapeStrategy::Auto] {
+            let escaped = escape_with(source, strategy, &syntax).unwrap();
+            assert_eq!(unescape(&escaped, &syntax).unwrap(), source);
+        }
+        assert_eq!(unescape(&escape_raw(source, &syntax).unwrap(), &syntax).unwrap(), source);
+
+        // Real template syntax and forms that render differently are kept
+        assert_eq!(unescape("{{ name }} {% if x %}{% endif %}", &syntax), None);
+        assert_eq!(
+            unescape("{{- '{' }} {%- raw %}a {% endraw %} {{ '{' }}", &syntax).unwrap(),
+            "{{- '{' }} {%- raw %}a {% endraw %} {"
+        );
+        assert_eq!(unescape("[['[']][ x ]] [% raw %][[ y ]][% endraw %]", &brackets()).unwrap(), "[[ x ]] [[ y ]]");
+    }
+
+    #[test]
+    fn test_escaped_tags_are_protected() {
+        let syntax = Syntax::default();
+        let escaped = escape("{{ {'a': {'b': 1}} }} app {% if app %}", &syntax).unwrap();
+        assert_eq!(
+            replace_unprotected(&escaped, &syntax, |text| Some(text.replace("app", "X")), |_| {}).unwrap(),
+            "{{'{'}}{ {'a': {'b': 1}} }} X {{'{'}}% if app %}"
+        );
+    }
+
+    #[test]
+    fn test_large_escaped_files_scan_in_one_pass() {
+        let syntax = Syntax::default();
+        let source = "app {{ a }} {# c #}\n".repeat(20_000) + &"{{ open\n".repeat(20_000);
+        let escaped = escape(&source, &syntax).unwrap();
+        let ranges = protected_ranges(&escaped, &syntax);
+        assert_eq!(ranges.len(), 40_000 + 20_000);
+        assert!(ranges.iter().all(|range| !escaped[range.clone()].contains("app")));
+    }
+
+    #[test]
+    fn test_reintroduces() {
+        assert!(!reintroduces("app", "{{ app }}", &Syntax::default()));
+        assert!(reintroduces("app", "my-app-{{ name }}", &Syntax::default()));
+        assert!(!reintroduces("app", "[[ app ]]", &brackets()));
+    }
+
+    #[test]
+    fn test_parse_syntax() {
+        assert_eq!(brackets().to_string(), "[[ ]] [% %] [# #]");
+        assert_eq!("<< >> <% %> <# #>".parse::<Syntax>().unwrap().comment, ("<#".into(), "#>".into()));
+        assert!("[[ ]]".parse::<Syntax>().is_err());
+        assert!("[[ ]] [[ ]]".parse::<Syntax>().is_err());
+        assert!("[[ ]] <: :>".parse::<Syntax>().is_err());
+    }
+
+    #[test]
+    fn test_custom_syntax() {
+        let syntax = brackets();
+        assert_eq!(
+            Syntax::default().translate("{{- name }} {% if x %}{# c #}{% raw %}{{ y }}{% endraw %}", &syntax),
+            "[[- name ]] [% if x %][# c #][% raw %]{{ y }}[% endraw %]"
+        );
+        assert_eq!(syntax.adopt("{{ a }} [[ b[0] ]]"), "[[ a ]] [[ b[0] ]]");
+
+        // Only collisions with the chosen delimiters need escaping
+        assert_eq!(
+            escape("{{ kept }} [[ x ]] a[b[0]]", &syntax).unwrap(),
+            "{{ kept }} [['[']][ x ]] a[b[0]]"
+        );
+        assert_eq!(
+            escape_with("[% if a %]\n{{ b }}", EscapeStrategy::Raw, &syntax).unwrap(),
+            "[% raw %][% if a %][% endraw %]\n{{ b }}"
+        );
+    }
+}
@@ -0,0 +1,412 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+use crate::transaction::{write_atomic, Change, Transaction};
+
+/// Directory at the root of a templatized project holding the journal and backups.
+pub const JOURNAL_DIRECTORY: &str = ".templatize";
+
+const JOURNAL_FILE_NAME: &str = "journal";
+const BACKUP_DIRECTORY: &str = "backups";
+
+/// One recorded run, as stored in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub id: u64,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The command that made the changes.
+    pub description: String,
+    /// Changes in the order they were applied, with paths relative to the project root.
+    pub changes: Vec<JournalChange>,
+    /// Original name of the project root, if the run renamed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_root: Option<String>,
+}
+
+/// A journaled filesystem change. Original contents of rewritten files are kept as backups.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalChange {
+    Write {
+        path: PathBuf,
+        original_hash: String,
+        new_hash: String,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    CreateDir {
+        path: PathBuf,
+    },
+    RemoveDir {
+        path: PathBuf,
+    },
+}
+
+impl Operation {
+    /// Number of files whose contents the run rewrote.
+    pub fn files_written(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| matches!(change, JournalChange::Write { .. }))
+            .count()
+    }
+
+    /// Number of files and directories the run renamed, including the project root.
+    pub fn paths_renamed(&self) -> usize {
+        let renames = self
+            .changes
+            .iter()
+            .filter(|change| matches!(change, JournalChange::Rename { .. }))
+            .count();
+        renames + usize::from(self.renamed_root.is_some())
+    }
+}
+
+/// The record of runs against a project, kept in [`JOURNAL_DIRECTORY`] at its root.
+///
+/// Each line of the journal file is one [`Operation`]; the original contents of every
+/// rewritten file are stored under `backups/<id>/<index>` so operations can be undone
+/// newest first.
+pub struct Journal {
+    root: PathBuf,
+}
+
+impl Journal {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    fn directory(&self) -> PathBuf {
+        self.root.join(JOURNAL_DIRECTORY)
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.directory().join(JOURNAL_FILE_NAME)
+    }
+
+    fn backup_directory(&self, id: u64) -> PathBuf {
+        self.directory().join(BACKUP_DIRECTORY).join(id.to_string())
+    }
+
+    fn backup_path(&self, id: u64, index: usize) -> PathBuf {
+        self.backup_directory(id).join(index.to_string())
+    }
+
+    /// Recorded operations, oldest first.
+    pub fn operations(&self) -> Result<Vec<Operation>> {
+        let path = self.journal_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&path).with_context(|| format!("Failed to read journal {:?}", path))?;
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).with_context(|| format!("Corrupt journal entry in {:?}", path)))
+            .collect()
+    }
+
+    /// Records `changes`, applied beneath `base`, as a new operation.
+    ///
+    /// `base` is where the project root was when the run started; it differs from the
+    /// journal's root when the run renamed the root itself.
+    pub fn record(&self, description: &str, changes: &[Change], base: &Path) -> Result<Operation> {
+        let mut operations = self.operations()?;
+        let id = operations.last().map_or(1, |operation| operation.id + 1);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+
+        let relative = |path: &Path| -> Result<PathBuf> {
+            path.strip_prefix(base)
+                .map(Path::to_path_buf)
+                .with_context(|| format!("{:?} is outside of {:?}", path, base))
+        };
+
+        // Whatever this call creates is removed again if recording fails
+        let new_directory = !self.directory().exists();
+        let new_backups = !self.directory().join(BACKUP_DIRECTORY).exists();
+        let recorded = (|| -> Result<Operation> {
+            self.ensure_directory()?;
+            let mut operation = Operation {
+                id,
+                timestamp,
+                description: description.to_string(),
+                changes: Vec::with_capacity(changes.len()),
+                renamed_root: None,
+            };
+            for (index, change) in changes.iter().enumerate() {
+                let change = match change {
+                    Change::Write { path, original } => {
+                        let backup = self.backup_path(id, operation.changes.len());
+                        if let Some(parent) = backup.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        fs::write(&backup, original).with_context(|| format!("Failed to back up {:?}", path))?;
+
+                        let written = final_location(path, &changes[index + 1..]);
+                        JournalChange::Write {
+                            path: relative(path)?,
+                            original_hash: hash(original),
+                            new_hash: hash(&fs::read(&written)?),
+                        }
+                    }
+                    Change::Rename { from, to } if from == base => {
+                        operation.renamed_root = Some(file_name(from)?);
+                        debug!("Project root renamed to {:?}", to);
+                        continue;
+                    }
+                    Change::Rename { from, to } => JournalChange::Rename {
+                        from: relative(from)?,
+                        to: relative(to)?,
+                    },
+                    Change::CreateDir { path } => JournalChange::CreateDir { path: relative(path)? },
+                    Change::RemoveDir { path } => JournalChange::RemoveDir { path: relative(path)? },
+                };
+                operation.changes.push(change);
+            }
+
+            operations.push(operation.clone());
+            self.save(&operations)?;
+            info!("Recorded operation {} in {:?}", id, self.journal_path());
+            Ok(operation)
+        })();
+
+        if recorded.is_err() {
+            let leftover = if new_directory {
+                self.directory()
+            } else if new_backups {
+                self.directory().join(BACKUP_DIRECTORY)
+            } else {
+                self.backup_directory(id)
+            };
+            if leftover.exists() {
+                if let Err(e) = fs::remove_dir_all(&leftover) {
+                    warn!("Failed to remove {:?}: {}", leftover, e);
+                }
+            }
+        }
+        recorded
+    }
+
+    /// Reverts the newest `count` operations, newest first, and removes them from the journal.
+    ///
+    /// Files changed since an operation are left alone unless `force` is set. The undo is
+    /// itself transactional: if any step fails, everything it reverted is re-applied. Returns
+    /// the undone operations and the project root, which moves if an undone run renamed it.
+    pub fn undo(&self, count: usize, force: bool) -> Result<(Vec<Operation>, PathBuf)> {
+        let mut operations = self.operations()?;
+        if operations.is_empty() {
+            anyhow::bail!("Nothing to undo: no operations recorded in {:?}", self.journal_path());
+        }
+        let count = count.min(operations.len());
+        let undone: Vec<Operation> = operations.split_off(operations.len() - count).into_iter().rev().collect();
+
+        let mut transaction = Transaction::new();
+        let mut root = self.root.clone();
+        let reverted = undone
+            .iter()
+            .try_for_each(|operation| revert(operation, &mut root, force, &mut transaction))
+            .and_then(|()| Journal::new(&root).save(&operations));
+        if let Err(e) = reverted {
+            return match transaction.rollback() {
+                Ok(()) => Err(e.context("Undo failed; nothing was changed")),
+                Err(rollback_error) => Err(e.context(format!("{:#}", rollback_error))),
+            };
+        }
+        transaction.commit();
+
+        let journal = Journal::new(&root);
+
+        for operation in &undone {
+            let backups = journal.backup_directory(operation.id);
+            if let Err(e) = fs::remove_dir_all(&backups) {
+                warn!("Failed to remove backups {:?}: {}", backups, e);
+            }
+        }
+        Ok((undone, root))
+    }
+
+    /// Creates the journal directory with a `.gitignore` so it never gets committed.
+    fn ensure_directory(&self) -> Result<()> {
+        let directory = self.directory();
+        if !directory.exists() {
+            fs::create_dir_all(&directory)?;
+            fs::write(directory.join(".gitignore"), "*\n")?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, operations: &[Operation]) -> Result<()> {
+        let mut contents = String::new();
+        for operation in operations {
+            contents.push_str(&serde_json::to_string(operation)?);
+            contents.push('\n');
+        }
+        write_atomic(&self.journal_path(), contents.as_bytes())
+    }
+}
+
+/// Reverts the changes of one operation, moving `root` back if the operation renamed it.
+fn revert(operation: &Operation, root: &mut PathBuf, force: bool, transaction: &mut Transaction) -> Result<()> {
+    info!("Undoing operation {}: {}", operation.id, operation.description);
+
+    // The root was renamed last, so it is restored first
+    if let Some(name) = &operation.renamed_root {
+        let original = root.with_file_name(name);
+        transaction.rename(root, &original)?;
+        *root = original;
+    }
+
+    for (index, change) in operation.changes.iter().enumerate().rev() {
+        match change {
+            JournalChange::Write { path, new_hash, .. } => {
+                let path = root.join(path);
+                let current = fs::read(&path).with_context(|| format!("Cannot restore {:?}", path))?;
+                if !force && hash(&current) != *new_hash {
+                    anyhow::bail!("{:?} was modified after operation {} (use --force to overwrite it)", path, operation.id);
+                }
+                let backup = Journal::new(root).backup_path(operation.id, index);
+                let original = fs::read(&backup).with_context(|| format!("Missing backup {:?}", backup))?;
+                transaction.write(&path, &original)?;
+            }
+            JournalChange::Rename { from, to } => {
+                let (from, to) = (root.join(from), root.join(to));
+                if from.exists() {
+                    anyhow::bail!("Cannot move {:?} back to {:?}: destination already exists", to, from);
+                }
+                transaction.rename(&to, &from)?;
+            }
+            JournalChange::CreateDir { path } => transaction.remove_dir(&root.join(path))?,
+            JournalChange::RemoveDir { path } => transaction.create_dir_all(&root.join(path))?,
+        }
+    }
+    Ok(())
+}
+
+/// Where a file written at `path` ended up after the rest of the run's renames.
+fn final_location(path: &Path, later: &[Change]) -> PathBuf {
+    let mut location = path.to_path_buf();
+    for change in later {
+        if let Change::Rename { from, to } = change {
+            if let Ok(rest) = location.strip_prefix(from) {
+                location = to.join(rest);
+            }
+        }
+    }
+    location
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .with_context(|| format!("Cannot journal the rename of {:?}", path))
+}
+
+fn hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_undo_restores_the_tree() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("acme");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/acme.txt"), "acme").unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.write(&root.join("src/acme.txt"), b"{{ org }}").unwrap();
+        transaction.rename(&root.join("src/acme.txt"), &root.join("src/{{ org }}.txt")).unwrap();
+        transaction.create_dir_all(&root.join("lib")).unwrap();
+        transaction.rename(&root, &temp.path().join("{{ org }}")).unwrap();
+        let changes = transaction.commit();
+
+        let renamed = temp.path().join("{{ org }}");
+        let journal = Journal::new(&renamed);
+        let operation = journal.record("exact acme {{ org }}", &changes, &root).unwrap();
+        assert_eq!(operation.id, 1);
+        assert_eq!(operation.renamed_root.as_deref(), Some("acme"));
+        assert_eq!(operation.files_written(), 1);
+        assert_eq!(operation.paths_renamed(), 2);
+        assert!(renamed.join(".templatize/.gitignore").is_file());
+
+        let (undone, restored_root) = journal.undo(1, false).unwrap();
+
+        assert_eq!(undone.len(), 1);
+        assert_eq!(restored_root, root);
+        assert_eq!(fs::read_to_string(root.join("src/acme.txt")).unwrap(), "acme");
+        assert!(!root.join("lib").exists());
+        assert!(Journal::new(&root).operations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_undo_refuses_modified_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.txt");
+        fs::write(&file, "acme").unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.write(&file, b"{{ org }}").unwrap();
+        let journal = Journal::new(temp.path());
+        journal.record("exact", &transaction.commit(), temp.path()).unwrap();
+        fs::write(&file, "edited").unwrap();
+
+        assert!(journal.undo(1, false).is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "edited");
+        assert_eq!(journal.operations().unwrap().len(), 1);
+
+        journal.undo(1, true).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "acme");
+    }
+
+    #[test]
+    fn test_failed_record_removes_its_backups() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.txt");
+        fs::write(&file, "acme").unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.write(&file, b"{{ org }}").unwrap();
+        let changes = transaction.commit();
+
+        // A directory where the journal is staged makes saving it fail
+        let journal = Journal::new(temp.path());
+        let staged = format!(".{}.templatize-{}.tmp", JOURNAL_FILE_NAME, std::process::id());
+        fs::create_dir_all(journal.directory().join(staged)).unwrap();
+
+        assert!(journal.record("exact", &changes, temp.path()).is_err());
+        assert!(!journal.directory().join(BACKUP_DIRECTORY).exists());
+        assert!(journal.operations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_record_leaves_no_journal_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        let project = temp.path().join("acme");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("a.txt"), "{{ org }}").unwrap();
+
+        // A change outside the project can't be recorded relative to it
+        let changes = [
+            Change::Write { path: project.join("a.txt"), original: b"acme".to_vec() },
+            Change::CreateDir { path: temp.path().join("elsewhere") },
+        ];
+        let journal = Journal::new(&project);
+
+        assert!(journal.record("exact", &changes, &project).is_err());
+        assert!(!journal.directory().exists());
+    }
+}
@@ -0,0 +1,347 @@
+use aho_corasick::{AhoCorasick, Input, MatchKind};
+use std::collections::HashMap;
+
+/// How an occurrence of a token must be delimited to count as a match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WordBoundary {
+    /// Match anywhere, including inside longer words.
+    #[default]
+    None,
+    /// Unicode word boundaries: the token may not touch a letter, digit or `_` on either side.
+    Unicode,
+    /// Identifier boundaries: like [`WordBoundary::Unicode`], but `-` joins words too, so
+    /// `app` matches neither `my_app` nor `app-config`.
+    Identifier,
+}
+
+impl WordBoundary {
+    /// Whether `c` continues a word under these semantics.
+    fn joins(self, c: char) -> bool {
+        match self {
+            WordBoundary::None => false,
+            WordBoundary::Unicode => c.is_alphanumeric() || c == '_',
+            WordBoundary::Identifier => c.is_alphanumeric() || c == '_' || c == '-',
+        }
+    }
+
+    /// Whether `haystack[start..end]` stands on its own rather than being part of a longer word.
+    ///
+    /// Only edges of the match that are themselves word characters need a boundary, so a
+    /// token such as `-core` still matches in `acme-core`.
+    pub fn is_delimited(self, haystack: &str, start: usize, end: usize) -> bool {
+        let matched = &haystack[start..end];
+        let starts_word = matched.chars().next().is_some_and(|c| self.joins(c));
+        let ends_word = matched.chars().next_back().is_some_and(|c| self.joins(c));
+
+        let joined_before = starts_word && haystack[..start].chars().next_back().is_some_and(|c| self.joins(c));
+        let joined_after = ends_word && haystack[end..].chars().next().is_some_and(|c| self.joins(c));
+        !joined_before && !joined_after
+    }
+
+    /// Byte ranges of the non-overlapping, delimited occurrences of `token`, left to right.
+    pub fn find(self, haystack: &str, token: &str) -> Vec<(usize, usize)> {
+        if token.is_empty() {
+            return Vec::new();
+        }
+        self.find_with(haystack, |from| {
+            haystack[from..]
+                .find(token)
+                .map(|offset| (from + offset, from + offset + token.len()))
+        })
+    }
+
+    /// Byte ranges of the delimited, non-empty matches found by `next_match`, which returns
+    /// the first candidate starting at or after a byte offset.
+    pub fn find_with<F>(self, haystack: &str, mut next_match: F) -> Vec<(usize, usize)>
+    where
+        F: FnMut(usize) -> Option<(usize, usize)>,
+    {
+        let mut matches = Vec::new();
+        let mut from = 0;
+        while from <= haystack.len() {
+            let Some((start, end)) = next_match(from) else {
+                break;
+            };
+            if start < end && self.is_delimited(haystack, start, end) {
+                matches.push((start, end));
+                from = end;
+            } else {
+                // Retry one character later, since a delimited match may overlap this one
+                from = start + haystack[start..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        matches
+    }
+
+    /// Replaces every delimited occurrence of `token`, returning `None` if there were none.
+    pub fn replace(self, haystack: &str, token: &str, replacement: &str) -> Option<String> {
+        replace_matches(haystack, &self.find(haystack, token), |_| replacement)
+    }
+}
+
+/// Replaces each of the sorted, non-overlapping `matches`, returning `None` if there are none.
+pub fn replace_matches<'r, F>(haystack: &str, matches: &[(usize, usize)], mut replacement: F) -> Option<String>
+where
+    F: FnMut(&str) -> &'r str,
+{
+    if matches.is_empty() {
+        return None;
+    }
+
+    let mut result = String::with_capacity(haystack.len());
+    let mut last = 0;
+    for &(start, end) in matches {
+        result.push_str(&haystack[last..start]);
+        result.push_str(replacement(&haystack[start..end]));
+        last = end;
+    }
+    result.push_str(&haystack[last..]);
+    Some(result)
+}
+
+/// Replaces many tokens at once in a single left-to-right pass.
+///
+/// At each position the longest token wins, and text that has been replaced is never scanned
+/// again, so a replacement that contains another token (or itself) is left as written.
+#[derive(Debug, Clone)]
+pub struct MultiReplacer {
+    automaton: AhoCorasick,
+    tokens: Vec<String>,
+    replacements: Vec<String>,
+    boundary: WordBoundary,
+}
+
+impl MultiReplacer {
+    /// Builds a replacer for `(token, replacement)` pairs; empty tokens are ignored, and the first
+    /// of several identical tokens wins.
+    pub fn new<T, R>(pairs: impl IntoIterator<Item = (T, R)>) -> Self
+    where
+        T: AsRef<str>,
+        R: Into<String>,
+    {
+        let mut tokens: Vec<String> = Vec::new();
+        let mut replacements = Vec::new();
+        for (token, replacement) in pairs {
+            let token = token.as_ref();
+            if !token.is_empty() && !tokens.iter().any(|existing| existing == token) {
+                tokens.push(token.to_string());
+                replacements.push(replacement.into());
+            }
+        }
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(&tokens)
+            .expect("literal tokens always build an automaton");
+        Self {
+            automaton,
+            tokens,
+            replacements,
+            boundary: WordBoundary::None,
+        }
+    }
+
+    /// Only replaces occurrences delimited by `boundary`.
+    pub fn with_word_boundary(mut self, boundary: WordBoundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// Passes every replacement through `rewrite`.
+    pub fn map_replacements<F: FnMut(&str) -> String>(mut self, mut rewrite: F) -> Self {
+        for replacement in &mut self.replacements {
+            *replacement = rewrite(replacement);
+        }
+        self
+    }
+
+    /// The `(token, replacement)` pairs, without ignored tokens.
+    pub fn pairs(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tokens.iter().map(String::as_str).zip(self.replacements.iter().map(String::as_str))
+    }
+
+    /// Byte ranges of the matches, left to right, each with the index of its replacement.
+    pub fn find(&self, haystack: &str) -> Vec<(usize, usize, usize)> {
+        let mut patterns = HashMap::new();
+        let ranges = self.boundary.find_with(haystack, |from| {
+            let found = self.automaton.find(Input::new(haystack).span(from..haystack.len()))?;
+            let (start, mut end, mut pattern) = (found.start(), found.end(), found.pattern().as_usize());
+            if !self.boundary.is_delimited(haystack, start, end) {
+                // A shorter token starting at the same place may still stand on its own
+                let shorter = self
+                    .tokens
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, token)| token.len() < end - start && haystack[start..].starts_with(token.as_str()))
+                    .filter(|(_, token)| self.boundary.is_delimited(haystack, start, start + token.len()))
+                    .max_by_key(|(_, token)| token.len());
+                if let Some((index, token)) = shorter {
+                    end = start + token.len();
+                    pattern = index;
+                }
+            }
+            patterns.insert(start, pattern);
+            Some((start, end))
+        });
+        ranges.into_iter().map(|(start, end)| (start, end, patterns[&start])).collect()
+    }
+
+    /// Replaces every match, returning `None` if there were none.
+    pub fn replace(&self, haystack: &str) -> Option<String> {
+        self.replace_counted(haystack).0
+    }
+
+    /// Replaces every match like [`MultiReplacer::replace`], also returning how many there were.
+    pub fn replace_counted(&self, haystack: &str) -> (Option<String>, usize) {
+        let matches = self.find(haystack);
+        let ranges: Vec<_> = matches.iter().map(|&(start, end, _)| (start, end)).collect();
+        let mut patterns = matches.iter().map(|&(_, _, pattern)| pattern);
+        let replaced = replace_matches(haystack, &ranges, |_| &self.replacements[patterns.next().unwrap()]);
+        (replaced, matches.len())
+    }
+}
+
+/// The casing style of a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LetterCase {
+    /// Every letter is uppercase, as in `ACME CORP`.
+    Upper,
+    /// Every letter is lowercase, as in `acme corp`.
+    Lower,
+    /// Every word starts with an uppercase letter followed by lowercase ones, as in `Acme Corp`.
+    Title,
+    /// Anything else, such as `AcMe corp` or `ACME Corp`.
+    Mixed,
+}
+
+impl LetterCase {
+    /// Classifies `text`, returning `None` when it has no cased letters at all.
+    pub fn of(text: &str) -> Option<Self> {
+        let cased: Vec<char> = text.chars().filter(|c| c.is_uppercase() || c.is_lowercase()).collect();
+        if cased.is_empty() {
+            return None;
+        }
+        if cased.iter().all(|c| c.is_uppercase()) {
+            return Some(LetterCase::Upper);
+        }
+        if cased.iter().all(|c| c.is_lowercase()) {
+            return Some(LetterCase::Lower);
+        }
+
+        let is_title = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .all(|word| {
+                let mut chars = word.chars();
+                chars.next().is_some_and(|first| !first.is_lowercase()) && chars.all(|c| !c.is_uppercase())
+            });
+        Some(if is_title { LetterCase::Title } else { LetterCase::Mixed })
+    }
+}
+
+/// Replacements for case-insensitive matches, chosen by the casing of each match.
+///
+/// Casings without a replacement of their own use the default replacement.
+#[derive(Debug, Clone, Default)]
+pub struct CaseReplacements {
+    pub upper: Option<String>,
+    pub lower: Option<String>,
+    pub title: Option<String>,
+    pub mixed: Option<String>,
+}
+
+impl CaseReplacements {
+    /// The replacement for a match cased as `case`, if one was configured.
+    pub fn get(&self, case: LetterCase) -> Option<&str> {
+        let replacement = match case {
+            LetterCase::Upper => &self.upper,
+            LetterCase::Lower => &self.lower,
+            LetterCase::Title => &self.title,
+            LetterCase::Mixed => &self.mixed,
+        };
+        replacement.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_boundary_matches_inside_words() {
+        let replaced = WordBoundary::None.replace("happy app wrapper", "app", "X");
+        assert_eq!(replaced.unwrap(), "hXy X wrXer");
+    }
+
+    #[test]
+    fn test_unicode_boundary() {
+        let boundary = WordBoundary::Unicode;
+        assert_eq!(
+            boundary.replace("app application my_app my-app app.rs", "app", "X").unwrap(),
+            "X application my_app my-X X.rs"
+        );
+        assert_eq!(boundary.replace("café app", "caf", "X"), None);
+        assert_eq!(boundary.replace("naïve-app", "naïve", "X").unwrap(), "X-app");
+    }
+
+    #[test]
+    fn test_identifier_boundary() {
+        let boundary = WordBoundary::Identifier;
+        assert_eq!(
+            boundary.replace("app my_app my-app app-config app.rs (app)", "app", "X").unwrap(),
+            "X my_app my-app app-config X.rs (X)"
+        );
+        assert_eq!(boundary.replace("acme-widgets-core", "acme-widgets", "X"), None);
+    }
+
+    #[test]
+    fn test_boundary_only_applies_to_word_edges() {
+        let boundary = WordBoundary::Unicode;
+        assert_eq!(boundary.replace("acme-core", "-core", "X").unwrap(), "acmeX");
+        assert_eq!(boundary.replace("src/acme/widgets", "acme/widgets", "X").unwrap(), "src/X");
+    }
+
+    #[test]
+    fn test_letter_case_classification() {
+        assert_eq!(LetterCase::of("ACME CORP"), Some(LetterCase::Upper));
+        assert_eq!(LetterCase::of("acme-corp"), Some(LetterCase::Lower));
+        assert_eq!(LetterCase::of("Acme Corp"), Some(LetterCase::Title));
+        assert_eq!(LetterCase::of("Acme"), Some(LetterCase::Title));
+        assert_eq!(LetterCase::of("AcMe corp"), Some(LetterCase::Mixed));
+        assert_eq!(LetterCase::of("ACME Corp"), Some(LetterCase::Mixed));
+        assert_eq!(LetterCase::of("Ärger Über"), Some(LetterCase::Title));
+        assert_eq!(LetterCase::of("42"), None);
+    }
+
+    #[test]
+    fn test_multi_replacer_is_single_pass_and_leftmost_longest() {
+        let replacer = MultiReplacer::new([
+            ("example", "{{ example-name }}"),
+            ("example-name", "{{ example_name }}"),
+            ("name", "{{ name }}"),
+        ]);
+        assert_eq!(
+            replacer.replace("example-name example name").unwrap(),
+            "{{ example_name }} {{ example-name }} {{ name }}"
+        );
+        assert_eq!(replacer.replace("nothing here"), None);
+        assert_eq!(replacer.replace_counted("example-name example name").1, 3);
+
+        let replacer = MultiReplacer::new([("app", "X"), ("core", "Y")]).with_word_boundary(WordBoundary::Unicode);
+        assert_eq!(replacer.replace("app happy core_lib core").unwrap(), "X happy core_lib Y");
+    }
+
+    #[test]
+    fn test_rejected_match_does_not_hide_a_later_one() {
+        assert_eq!(WordBoundary::Unicode.replace("aa a", "a", "X").unwrap(), "aa X");
+        assert_eq!(WordBoundary::Unicode.find("xab ab", "ab"), vec![(4, 6)]);
+    }
+
+    #[test]
+    fn test_rejected_long_token_falls_back_to_a_shorter_one() {
+        let replacer = MultiReplacer::new([("app", "X"), ("app-name", "Y")]).with_word_boundary(WordBoundary::Unicode);
+        assert_eq!(replacer.replace("app-names app-name").unwrap(), "X-names Y");
+
+        let replacer = MultiReplacer::new([("a", "1"), ("ab", "2"), ("abc", "3")]).with_word_boundary(WordBoundary::Unicode);
+        assert_eq!(replacer.replace("abcd ab+").unwrap(), "abcd 2+");
+    }
+}
@@ -0,0 +1,265 @@
+use crate::jinja::{self, SegmentKind, Syntax};
+
+/// Names that are part of Jinja's expression syntax rather than variables.
+const KEYWORDS: &[&str] = &[
+    "and", "or", "not", "in", "is", "if", "else", "true", "false", "none", "True", "False", "None",
+];
+
+/// A replacement template such as `{{ org }}-{{ name | upper }}`, parsed so that its variables
+/// can be renamed or filtered while filters, literals and spacing are kept exactly as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplacementTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Expression {
+        /// `{{` with any whitespace-control mark.
+        open: String,
+        tokens: Vec<Token>,
+        /// `}}` with any whitespace-control mark.
+        close: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Whitespace(String),
+    /// A name, which may join words with `-` as in `project-name`.
+    Name(String),
+    /// A quoted string, quotes included.
+    Str(String),
+    Number(String),
+    Punct(char),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Whitespace(text) | Token::Name(text) | Token::Str(text) | Token::Number(text) => text.clone(),
+            Token::Punct(c) => c.to_string(),
+        }
+    }
+}
+
+impl ReplacementTemplate {
+    pub fn parse(text: &str) -> Self {
+        let segments = jinja::lex(text, &Syntax::default())
+            .into_iter()
+            .map(|segment| match segment.kind {
+                SegmentKind::Expression => Segment::Expression {
+                    open: text[segment.range.start..segment.inner.start].to_string(),
+                    tokens: tokenize(segment.inner(text)),
+                    close: text[segment.inner.end..segment.range.end].to_string(),
+                },
+                _ => Segment::Literal(text[segment.range].to_string()),
+            })
+            .collect();
+        Self { segments }
+    }
+
+    /// Whether the template holds any `{{ ... }}` expression.
+    pub fn has_expressions(&self) -> bool {
+        self.segments.iter().any(|segment| matches!(segment, Segment::Expression { .. }))
+    }
+
+    /// Names of the variables the expressions refer to, in order of appearance.
+    pub fn variables(&self) -> Vec<String> {
+        self.expressions()
+            .flat_map(|tokens| variable_positions(tokens).into_iter().map(|i| tokens[i].text()))
+            .collect()
+    }
+
+    /// The template with every variable name passed through `rename`.
+    pub fn rename_variables<F: Fn(&str) -> String>(&self, rename: F) -> String {
+        self.render(|tokens| {
+            let mut tokens = tokens.to_vec();
+            for i in variable_positions(&tokens) {
+                tokens[i] = Token::Name(rename(&tokens[i].text()));
+            }
+            tokens
+        })
+    }
+
+    /// The template with `filters` applied, in order, to every variable reference.
+    pub fn filter_variables(&self, filters: &[&str]) -> String {
+        let piped: String = filters.iter().map(|filter| format!(" | {}", filter)).collect();
+        self.render(|tokens| {
+            let mut filtered = Vec::new();
+            let mut from = 0;
+            for i in variable_positions(tokens) {
+                let end = reference_end(tokens, i);
+                filtered.extend_from_slice(&tokens[from..end]);
+                filtered.push(Token::Whitespace(piped.clone()));
+                from = end;
+            }
+            filtered.extend_from_slice(&tokens[from..]);
+            filtered
+        })
+    }
+
+    fn expressions(&self) -> impl Iterator<Item = &[Token]> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Expression { tokens, .. } => Some(tokens.as_slice()),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    fn render<F: Fn(&[Token]) -> Vec<Token>>(&self, rewrite: F) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Expression { open, tokens, close } => {
+                    output.push_str(open);
+                    rewrite(tokens).iter().for_each(|token| output.push_str(&token.text()));
+                    output.push_str(close);
+                }
+            }
+        }
+        output
+    }
+}
+
+fn tokenize(expression: &str) -> Vec<Token> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        i += 1;
+        let token = if c.is_whitespace() {
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            Token::Whitespace(chars[start..i].iter().collect())
+        } else if c == '\'' || c == '"' {
+            while i < chars.len() && chars[i] != c {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(chars.len());
+            Token::Str(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            Token::Number(chars[start..i].iter().collect())
+        } else if c.is_alphabetic() || c == '_' {
+            loop {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                // `project-name` is one name, while `count - 1` and `count-1` are subtractions
+                let joins = chars.get(i) == Some(&'-') && chars.get(i + 1).is_some_and(|c| c.is_alphabetic() || *c == '_');
+                if !joins {
+                    break;
+                }
+                i += 1;
+            }
+            Token::Name(chars[start..i].iter().collect())
+        } else {
+            Token::Punct(c)
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Indices of the names in `tokens` that refer to variables, leaving out filter and test names,
+/// attributes, keyword arguments, called functions and keywords.
+fn variable_positions(tokens: &[Token]) -> Vec<usize> {
+    let significant: Vec<usize> = (0..tokens.len())
+        .filter(|&i| !matches!(tokens[i], Token::Whitespace(_)))
+        .collect();
+    let mut positions = Vec::new();
+    for (n, &i) in significant.iter().enumerate() {
+        let Token::Name(name) = &tokens[i] else {
+            continue;
+        };
+        let before = |back: usize| n.checked_sub(back).map(|m| &tokens[significant[m]]);
+        let after = |ahead: usize| significant.get(n + ahead).map(|&m| &tokens[m]);
+        let is_name = |token: Option<&Token>, expected: &str| matches!(token, Some(Token::Name(name)) if name == expected);
+
+        let filter_or_attribute = matches!(before(1), Some(Token::Punct('|' | '.')));
+        let test = is_name(before(1), "is") || (is_name(before(1), "not") && is_name(before(2), "is"));
+        let keyword_argument = after(1) == Some(&Token::Punct('=')) && tokens.get(significant[n + 1] + 1) != Some(&Token::Punct('='));
+        let call = after(1) == Some(&Token::Punct('('));
+        if !(filter_or_attribute || test || keyword_argument || call || KEYWORDS.contains(&name.as_str())) {
+            positions.push(i);
+        }
+    }
+    positions
+}
+
+/// The index just past the reference starting at the name `tokens[start]`, including attribute
+/// access, subscripts and calls chained onto it.
+fn reference_end(tokens: &[Token], start: usize) -> usize {
+    let mut end = start + 1;
+    loop {
+        match (tokens.get(end), tokens.get(end + 1)) {
+            (Some(Token::Punct('.')), Some(Token::Name(_))) => end += 2,
+            (Some(Token::Punct(open @ ('[' | '('))), _) => {
+                let close = if *open == '[' { ']' } else { ')' };
+                let mut depth = 0;
+                while let Some(token) = tokens.get(end) {
+                    end += 1;
+                    match token {
+                        Token::Punct(c) if c == open => depth += 1,
+                        Token::Punct(c) if *c == close => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => return end,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trips() {
+        for text in [
+            "{{ project-name }}",
+            "{{org}}-{{ name | upper }}.rs",
+            "{{- {'a': {'b': 1}} -}}",
+            "{{ name | replace('}}', \"x\") }} and {{ unclosed",
+            "plain text",
+        ] {
+            assert_eq!(ReplacementTemplate::parse(text).rename_variables(str::to_string), text);
+        }
+    }
+
+    #[test]
+    fn test_variables() {
+        let template = ReplacementTemplate::parse(
+            "{{ org }}-{{ name | default(fallback, boolean=true) }} {{ item.id is not defined }} {{ range(3) }}",
+        );
+        assert_eq!(template.variables(), ["org", "name", "fallback", "item"]);
+        assert!(!ReplacementTemplate::parse("no expressions").has_expressions());
+    }
+
+    #[test]
+    fn test_rename_and_filter_variables() {
+        let template = ReplacementTemplate::parse("com.{{ org-name }}.{{ app_name | lower }}");
+        assert_eq!(template.rename_variables(str::to_uppercase), "com.{{ ORG-NAME }}.{{ APP_NAME | lower }}");
+        assert_eq!(
+            template.filter_variables(&["camel_case"]),
+            "com.{{ org-name | camel_case }}.{{ app_name | camel_case | lower }}"
+        );
+        assert_eq!(
+            ReplacementTemplate::parse("{{ item.name ~ suffix[0] }}").filter_variables(&["pluralize", "snake_case"]),
+            "{{ item.name | pluralize | snake_case ~ suffix[0] | pluralize | snake_case }}"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::inflect::Inflection;
+use crate::words::WordSplitter;
+
+/// A way of writing a compound word, such as `camelCase` or `dot.case`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaseShape {
+    Camel,
+    Pascal,
+    Kebab,
+    Snake,
+    Train,
+    ScreamingSnake,
+    Cobol,
+    Dot,
+    Path,
+    Flat,
+    UpperFlat,
+    Title,
+    Sentence,
+    Lower,
+}
+
+impl CaseShape {
+    /// Every supported shape, in the order mappings are listed.
+    pub const ALL: [CaseShape; 14] = [
+        CaseShape::Camel,
+        CaseShape::Pascal,
+        CaseShape::Kebab,
+        CaseShape::Snake,
+        CaseShape::Train,
+        CaseShape::ScreamingSnake,
+        CaseShape::Cobol,
+        CaseShape::Dot,
+        CaseShape::Path,
+        CaseShape::Flat,
+        CaseShape::UpperFlat,
+        CaseShape::Title,
+        CaseShape::Sentence,
+        CaseShape::Lower,
+    ];
+
+    /// The name used to select this shape, as in `--cases camel,kebab`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CaseShape::Camel => "camel",
+            CaseShape::Pascal => "pascal",
+            CaseShape::Kebab => "kebab",
+            CaseShape::Snake => "snake",
+            CaseShape::Train => "train",
+            CaseShape::ScreamingSnake => "screaming-snake",
+            CaseShape::Cobol => "cobol",
+            CaseShape::Dot => "dot",
+            CaseShape::Path => "path",
+            CaseShape::Flat => "flat",
+            CaseShape::UpperFlat => "upper-flat",
+            CaseShape::Title => "title",
+            CaseShape::Sentence => "sentence",
+            CaseShape::Lower => "lower",
+        }
+    }
+
+    /// `example-name` written in this shape.
+    pub fn example(self) -> &'static str {
+        match self {
+            CaseShape::Camel => "exampleName",
+            CaseShape::Pascal => "ExampleName",
+            CaseShape::Kebab => "example-name",
+            CaseShape::Snake => "example_name",
+            CaseShape::Train => "Example-Name",
+            CaseShape::ScreamingSnake => "EXAMPLE_NAME",
+            CaseShape::Cobol => "EXAMPLE-NAME",
+            CaseShape::Dot => "example.name",
+            CaseShape::Path => "example/name",
+            CaseShape::Flat => "examplename",
+            CaseShape::UpperFlat => "EXAMPLENAME",
+            CaseShape::Title => "Example Name",
+            CaseShape::Sentence => "Example name",
+            CaseShape::Lower => "example name",
+        }
+    }
+
+    /// The Jinja filter that renders a variable in this shape, following Archetect's names.
+    pub fn filter(self) -> &'static str {
+        match self {
+            CaseShape::Camel => "camel_case",
+            CaseShape::Pascal => "pascal_case",
+            CaseShape::Kebab => "kebab_case",
+            CaseShape::Snake => "snake_case",
+            CaseShape::Train => "train_case",
+            CaseShape::ScreamingSnake => "constant_case",
+            CaseShape::Cobol => "cobol_case",
+            CaseShape::Dot => "dot_case",
+            CaseShape::Path => "directory_case",
+            CaseShape::Flat => "flat_case",
+            CaseShape::UpperFlat => "upper_flat_case",
+            CaseShape::Title => "title_case",
+            CaseShape::Sentence => "sentence_case",
+            CaseShape::Lower => "lower_case",
+        }
+    }
+
+    /// Whether words joined in this shape still form a name a template can refer to, as opposed
+    /// to `example.name` (an attribute lookup), `example/name` (a division) or `Example Name`.
+    pub fn is_identifier(self) -> bool {
+        !matches!(
+            self,
+            CaseShape::Dot | CaseShape::Path | CaseShape::Title | CaseShape::Sentence | CaseShape::Lower
+        )
+    }
+
+    /// Rewrites the words of `text` in this shape.
+    pub fn apply(self, text: &str) -> String {
+        let splitter = WordSplitter::default();
+        self.join(&splitter.split(text), &splitter)
+    }
+
+    /// Writes lowercase `words` in this shape, spelling acronyms as `splitter` knows them.
+    pub fn join<S: AsRef<str>>(self, words: &[S], splitter: &WordSplitter) -> String {
+        let lower = || words.iter().map(|word| word.as_ref().to_string());
+        let upper = || words.iter().map(|word| word.as_ref().to_uppercase());
+        let capitalized = || words.iter().map(|word| splitter.capitalize(word.as_ref()));
+        match self {
+            CaseShape::Camel => lower().take(1).chain(capitalized().skip(1)).collect(),
+            CaseShape::Pascal => capitalized().collect(),
+            CaseShape::Kebab => lower().collect::<Vec<_>>().join("-"),
+            CaseShape::Snake => lower().collect::<Vec<_>>().join("_"),
+            CaseShape::Train => capitalized().collect::<Vec<_>>().join("-"),
+            CaseShape::ScreamingSnake => upper().collect::<Vec<_>>().join("_"),
+            CaseShape::Cobol => upper().collect::<Vec<_>>().join("-"),
+            CaseShape::Dot => lower().collect::<Vec<_>>().join("."),
+            CaseShape::Path => lower().collect::<Vec<_>>().join("/"),
+            CaseShape::Flat => lower().collect(),
+            CaseShape::UpperFlat => upper().collect(),
+            CaseShape::Title => capitalized().collect::<Vec<_>>().join(" "),
+            CaseShape::Sentence => capitalized()
+                .take(1)
+                .chain(words.iter().skip(1).map(|word| {
+                    let word = word.as_ref();
+                    if splitter.is_acronym(word) { splitter.capitalize(word) } else { word.to_string() }
+                }))
+                .collect::<Vec<_>>()
+                .join(" "),
+            CaseShape::Lower => lower().collect::<Vec<_>>().join(" "),
+        }
+    }
+}
+
+impl fmt::Display for CaseShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CaseShape {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let normalized = name.trim().to_lowercase().replace('_', "-");
+        CaseShape::ALL
+            .into_iter()
+            .find(|shape| shape.name() == normalized)
+            .ok_or_else(|| {
+                let names: Vec<_> = CaseShape::ALL.iter().map(|shape| shape.name()).collect();
+                format!("Unknown case shape '{}' (expected one of: {})", name, names.join(", "))
+            })
+    }
+}
+
+/// A case shape or inflection that can be given its own filter name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterSlot {
+    Shape(CaseShape),
+    Inflection(Inflection),
+}
+
+impl FilterSlot {
+    /// The filter used when no other name is configured.
+    pub fn default_filter(self) -> &'static str {
+        match self {
+            FilterSlot::Shape(shape) => shape.filter(),
+            FilterSlot::Inflection(inflection) => inflection.filter(),
+        }
+    }
+}
+
+impl From<CaseShape> for FilterSlot {
+    fn from(shape: CaseShape) -> Self {
+        FilterSlot::Shape(shape)
+    }
+}
+
+impl From<Inflection> for FilterSlot {
+    fn from(inflection: Inflection) -> Self {
+        FilterSlot::Inflection(inflection)
+    }
+}
+
+impl FromStr for FilterSlot {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "plural" => Ok(FilterSlot::Inflection(Inflection::Plural)),
+            "singular" => Ok(FilterSlot::Inflection(Inflection::Singular)),
+            _ => name.parse::<CaseShape>().map(FilterSlot::Shape).map_err(|_| {
+                let names: Vec<_> = CaseShape::ALL
+                    .iter()
+                    .map(|shape| shape.name())
+                    .chain(["plural", "singular"])
+                    .collect();
+                format!("Unknown case shape or inflection '{}' (expected one of: {})", name, names.join(", "))
+            }),
+        }
+    }
+}
+
+/// Filter names used for filter-style shape replacements, such as `{{ name | camel_case }}`.
+///
+/// Slots without an override use [`FilterSlot::default_filter`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaseFilters {
+    overrides: HashMap<FilterSlot, String>,
+}
+
+impl CaseFilters {
+    /// Renders `slot` with the filter `name` instead of the default one.
+    pub fn with_name(mut self, slot: impl Into<FilterSlot>, name: &str) -> Self {
+        self.overrides.insert(slot.into(), name.to_string());
+        self
+    }
+
+    /// The filter to apply for `slot`.
+    pub fn name(&self, slot: impl Into<FilterSlot>) -> &str {
+        let slot = slot.into();
+        self.overrides.get(&slot).map_or(slot.default_filter(), String::as_str)
+    }
+}
+
+/// Settings for the shapes command.
+#[derive(Debug, Clone, Default)]
+pub struct ShapeOptions {
+    /// Case shapes to generate; empty means all of them.
+    pub shapes: Vec<CaseShape>,
+    /// Keep the replacement's variable and apply a case filter per shape, instead of
+    /// renaming the variable in each shape.
+    pub filters: Option<CaseFilters>,
+    /// Also replace the other grammatical number of the token's last word, so `order-item`
+    /// brings in `order-items` and `OrderItems`.
+    pub inflect: bool,
+    /// Acronyms to keep together when splitting words, written as given in capitalized
+    /// shapes (`ID`, `URL`, `OAuth`).
+    pub acronyms: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_shape_matches_its_example() {
+        for shape in CaseShape::ALL {
+            assert_eq!(shape.apply("example-name"), shape.example(), "{}", shape);
+            assert_eq!(shape.apply("ExampleName"), shape.example(), "{}", shape);
+        }
+    }
+
+    #[test]
+    fn test_shapes_with_acronyms_and_digits() {
+        let splitter = WordSplitter::new(&["OAuth", "ID"]);
+        let words = splitter.split("oauth2-client-id");
+        assert_eq!(CaseShape::Pascal.join(&words, &splitter), "OAuth2ClientID");
+        assert_eq!(CaseShape::Camel.join(&words, &splitter), "oauth2ClientID");
+        assert_eq!(CaseShape::Sentence.join(&words, &splitter), "OAuth2 client ID");
+        assert_eq!(CaseShape::ScreamingSnake.join(&words, &splitter), "OAUTH2_CLIENT_ID");
+        assert_eq!(CaseShape::Pascal.apply("v2-api"), "V2Api");
+    }
+
+    #[test]
+    fn test_shape_names_round_trip() {
+        for shape in CaseShape::ALL {
+            assert_eq!(shape.name().parse::<CaseShape>().unwrap(), shape);
+        }
+        assert_eq!("SCREAMING_SNAKE".parse::<CaseShape>().unwrap(), CaseShape::ScreamingSnake);
+        assert!("klingon".parse::<CaseShape>().is_err());
+    }
+
+    #[test]
+    fn test_case_filter_overrides() {
+        let filters = CaseFilters::default().with_name(CaseShape::Pascal, "class_case");
+        assert_eq!(filters.name(CaseShape::Pascal), "class_case");
+        assert_eq!(filters.name(CaseShape::ScreamingSnake), "constant_case");
+        assert_eq!(filters.name(Inflection::Plural), "pluralize");
+    }
+
+    #[test]
+    fn test_filter_slot_names() {
+        assert_eq!("plural".parse::<FilterSlot>().unwrap(), FilterSlot::Inflection(Inflection::Plural));
+        assert_eq!("camel".parse::<FilterSlot>().unwrap(), FilterSlot::Shape(CaseShape::Camel));
+        let error = "klingon".parse::<FilterSlot>().unwrap_err();
+        assert!(error.ends_with("lower, plural, singular)"), "{}", error);
+    }
+}
@@ -1,32 +1,268 @@
-use std::path::{Path, PathBuf};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use tracing::debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Once};
+use tracing::{debug, info, warn};
 use regex::Regex;
-use convert_case::{Case, Casing};
+use serde::Serialize;
+
+use crate::dialect::{DialectReport, EscapePlan};
+use crate::inflect::Inflection;
+use crate::jinja::{self, EscapeStrategy, Syntax};
+use crate::matching::{replace_matches, CaseReplacements, LetterCase, MultiReplacer, WordBoundary};
+use crate::replacement::ReplacementTemplate;
+use crate::shapes::{CaseShape, ShapeOptions};
+use crate::words::WordSplitter;
+
+/// A transformation applied to file contents and path names while walking a target.
+///
+/// Implementors return `None` when the input is left untouched, which lets the
+/// walker skip writes, renames and interactive prompts for unchanged entries.
+pub trait Templater {
+    /// Transforms file contents, returning the new contents if anything changed.
+    fn process_content(&self, content: &str) -> Option<String>;
+
+    /// Transforms the contents of the file at `relative`, for templaters that treat some files
+    /// differently; the default ignores the path and calls [`Templater::process_content`].
+    fn process_file_content(&self, _relative: &Path, content: &str) -> Option<String> {
+        self.process_content(content)
+    }
+
+    /// Transforms the final component of `path`, returning the new file name if it changed.
+    fn process_path_component(&self, _path: &Path) -> Option<String> {
+        None
+    }
+
+    /// Transforms a whole relative path, allowing replacements that span several segments.
+    ///
+    /// The default applies [`Templater::process_path_component`] to each segment in turn.
+    fn process_full_path(&self, path: &Path) -> Option<PathBuf> {
+        let mut changed = false;
+        let mut new_path = PathBuf::new();
+        for component in path.components() {
+            let segment = Path::new(component.as_os_str());
+            match self.process_path_component(segment) {
+                Some(new_name) => {
+                    changed = true;
+                    new_path.push(new_name);
+                }
+                None => new_path.push(segment),
+            }
+        }
+        changed.then_some(new_path)
+    }
+
+    /// Description passed to interactive content callbacks.
+    fn content_change_description(&self) -> &str {
+        "Content change"
+    }
+
+    /// Called once the contents returned for the file at `relative` have been accepted and
+    /// applied, so templaters that keep notes about their changes can drop rejected ones.
+    fn content_applied(&self, _relative: &Path) {}
+
+    /// Takes the number of occurrences that content processing has left alone inside existing
+    /// template syntax since the last call.
+    fn take_protected(&self) -> usize {
+        0
+    }
+}
 
 pub struct ExactTemplater {
     token: String,
     replacement: String,
+    boundary: WordBoundary,
+    /// Case-insensitive patterns for contents and for `/`-normalized paths, when ignoring case.
+    ignore_case: Option<(Regex, Regex)>,
+    case_replacements: CaseReplacements,
+    syntax: Syntax,
+    protect: bool,
+    /// Set once replacements that reintroduce their token have been warned about.
+    checked: Once,
+    /// Occurrences left inside template syntax by content processing, until taken.
+    protected: Cell<usize>,
+}
+
+/// Replaces several exact tokens in a single pass, preferring the longest token at each position.
+pub struct MultiExactTemplater {
+    replacer: MultiReplacer,
+    syntax: Syntax,
+    protect: bool,
+    checked: Once,
+    protected: Cell<usize>,
+}
+
+/// Replaces matches of a regular expression; the replacement may refer to capture groups
+/// as `$1`, `${1}` or `${name}`.
+pub struct RegexTemplater {
+    pattern: Regex,
+    replacement: String,
 }
 
+/// Escapes Jinja syntax so that rendering the escaped text reproduces the original.
+#[derive(Debug, Clone, Default)]
 pub struct JinjaEscaper {
-    jinja_pattern: Regex,
+    strategy: EscapeStrategy,
+    syntax: Syntax,
+    /// Reports for files escaped but not yet applied, keyed by relative path.
+    pending: RefCell<HashMap<PathBuf, DialectReport>>,
+    reports: RefCell<Vec<DialectReport>>,
+}
+
+/// Reverses [`JinjaEscaper`], turning escaped template syntax back into the text it renders as.
+#[derive(Debug, Clone, Default)]
+pub struct JinjaUnescaper {
+    syntax: Syntax,
 }
 
 pub struct CaseShapeTemplater {
-    replacements: HashMap<String, String>,
+    /// Mappings in [`CaseShape::ALL`] order, followed by the token as given unless a shape produced
+    /// it, then any inflected mappings.
+    mappings: Vec<CaseShapeMapping>,
+    replacer: MultiReplacer,
+    syntax: Syntax,
+    protect: bool,
+    checked: Once,
+    protected: Cell<usize>,
 }
 
-#[derive(Debug, Clone)]
+/// Shapes generated by default for single-word tokens.
+const SINGLE_WORD_SHAPES: [CaseShape; 3] = [CaseShape::Snake, CaseShape::Pascal, CaseShape::ScreamingSnake];
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CaseShapeMapping {
+    /// Name of the case shape, or `original` for the token as given.
+    pub shape: String,
+    /// Set when the token's last word was turned into its other grammatical number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inflection: Option<Inflection>,
     pub original: String,
     pub replacement: String,
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct TemplateOptions {
     pub process_paths: bool,
     pub process_contents: bool,
     pub dry_run: bool,
+    /// Walk everything instead of honoring `.gitignore`, global git excludes and `.templatizeignore`.
+    pub no_ignore: bool,
+    /// Globs restricting which files are processed; empty means every file.
+    pub include: Vec<String>,
+    /// Globs for files and directories to leave out entirely.
+    pub exclude: Vec<String>,
+    /// Globs limiting content templating; empty means every processed file.
+    pub content_scope: Vec<String>,
+    /// Globs limiting path templating; empty means every processed entry.
+    pub path_scope: Vec<String>,
+    /// Files larger than this many bytes are left untouched.
+    pub max_file_size: Option<u64>,
+    /// Encoding label for text that isn't UTF-8 or UTF-16; defaults to Windows-1252.
+    pub fallback_encoding: Option<String>,
+    /// When set (for example from a Ctrl-C handler), the run stops and rolls back.
+    pub cancel_flag: Option<Arc<AtomicBool>>,
+    /// Boundaries an exact token needs to match; by default it matches anywhere.
+    pub word_boundary: WordBoundary,
+    /// Match exact tokens in any casing.
+    pub ignore_case: bool,
+    /// Replacements for case-insensitive matches by casing, overriding the default replacement.
+    pub case_replacements: CaseReplacements,
+    /// Shapes generated by the shapes command and how their replacements are written.
+    pub shapes: ShapeOptions,
+    /// Delimiters of the generated template; replacements may use these or the standard ones.
+    pub syntax: Syntax,
+    /// Replace tokens inside existing template syntax too, for code such as Rust's
+    /// `format!("{{name}}")` that uses the same delimiters for something else.
+    pub no_protect: bool,
+    /// How the escape command writes escaped template syntax.
+    pub escape_strategy: EscapeStrategy,
+    /// Description to record the run under in the project's journal; `None` skips journaling.
+    pub journal: Option<String>,
+}
+
+/// The `(token, replacement)` pairs whose replacement contains its own token where running
+/// the same replacement again would replace it again: outside template syntax in `syntax`, or
+/// anywhere when template syntax isn't protected.
+fn reintroductions<'p>(
+    pairs: impl IntoIterator<Item = (&'p str, &'p str)>,
+    syntax: &Syntax,
+    protect: bool,
+) -> Vec<(&'p str, &'p str)> {
+    pairs
+        .into_iter()
+        .filter(|&(token, replacement)| match protect {
+            true => jinja::reintroduces(token, replacement, syntax),
+            false => !token.is_empty() && replacement.contains(token),
+        })
+        .collect()
+}
+
+fn warn_reintroduced(pairs: &[(&str, &str)]) {
+    for (token, replacement) in pairs {
+        warn!(
+            "The replacement '{}' contains its own token '{}' outside template syntax, \
+             so running it again will replace it again",
+            replacement, token
+        );
+    }
+}
+
+/// What replacing outside template syntax did.
+struct Replaced {
+    /// The new text, if anything was replaced.
+    text: Option<String>,
+    /// Occurrences replaced.
+    count: usize,
+    /// Occurrences left alone inside existing template syntax.
+    protected: usize,
+}
+
+/// Applies `replace`, which returns the new text and how many occurrences it replaced, to the
+/// text of `haystack` outside template syntax when `protect` is set, and to all of it otherwise.
+///
+/// Also counts the occurrences `count` finds in the template syntax left alone, so that they
+/// don't go unnoticed.
+fn replace_outside_templates<F, C>(haystack: &str, syntax: &Syntax, protect: bool, mut replace: F, mut count: C) -> Replaced
+where
+    F: FnMut(&str) -> (Option<String>, usize),
+    C: FnMut(&str) -> usize,
+{
+    if !protect {
+        let (text, count) = replace(haystack);
+        return Replaced { text, count, protected: 0 };
+    }
+    let (mut replaced, mut protected) = (0, 0);
+    let text = jinja::replace_unprotected(
+        haystack,
+        syntax,
+        |text| {
+            let (new_text, count) = replace(text);
+            replaced += count;
+            new_text
+        },
+        |text| protected += count(text),
+    );
+    Replaced { text, count: replaced, protected }
+}
+
+/// Matches of `replacer` in the protected `text`, unless it is one of the replacements as
+/// written by an earlier run.
+fn count_protected(replacer: &MultiReplacer, text: &str) -> usize {
+    if replacer.pairs().any(|(_, replacement)| replacement == text) {
+        return 0;
+    }
+    replacer.find(text).len()
+}
+
+fn adopt_case_replacements(case_replacements: &CaseReplacements, syntax: &Syntax) -> CaseReplacements {
+    let adopt = |replacement: &Option<String>| replacement.as_deref().map(|replacement| syntax.adopt(replacement));
+    CaseReplacements {
+        upper: adopt(&case_replacements.upper),
+        lower: adopt(&case_replacements.lower),
+        title: adopt(&case_replacements.title),
+        mixed: adopt(&case_replacements.mixed),
+    }
 }
 
 impl ExactTemplater {
@@ -34,24 +270,108 @@ impl ExactTemplater {
         Self {
             token: token.to_string(),
             replacement: replacement.to_string(),
+            boundary: WordBoundary::None,
+            ignore_case: None,
+            case_replacements: CaseReplacements::default(),
+            syntax: Syntax::default(),
+            protect: true,
+            checked: Once::new(),
+            protected: Cell::new(0),
         }
     }
 
-    pub fn process_content(&self, content: &str) -> Option<String> {
-        if content.contains(&self.token) {
-            let new_content = content.replace(&self.token, &self.replacement);
-            debug!("Content replacement: found {} occurrences", content.matches(&self.token).count());
-            Some(new_content)
-        } else {
-            None
+    /// Whether text inside existing template syntax is left alone, which is the default.
+    pub fn with_protection(mut self, protect: bool) -> Self {
+        self.protect = protect;
+        self
+    }
+
+    /// Writes the replacements in `syntax` and leaves text in that syntax alone.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.replacement = syntax.adopt(&self.replacement);
+        self.case_replacements = adopt_case_replacements(&self.case_replacements, &syntax);
+        self.syntax = syntax;
+        self
+    }
+
+    /// Only replaces occurrences of the token delimited by `boundary`.
+    pub fn with_word_boundary(mut self, boundary: WordBoundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// Matches the token in any casing, replacing each match according to its casing.
+    pub fn with_ignore_case(mut self, case_replacements: CaseReplacements) -> Self {
+        let pattern = |token: &str| {
+            Regex::new(&format!("(?i){}", regex::escape(token))).expect("an escaped token is a valid pattern")
+        };
+        self.ignore_case = Some((pattern(&self.token), pattern(&self.token.replace('\\', "/"))));
+        self.case_replacements = adopt_case_replacements(&case_replacements, &self.syntax);
+        self
+    }
+
+    fn find(&self, haystack: &str, token: &str, is_path: bool) -> Vec<(usize, usize)> {
+        match &self.ignore_case {
+            Some((content_pattern, path_pattern)) => {
+                let pattern = if is_path { path_pattern } else { content_pattern };
+                self.boundary
+                    .find_with(haystack, |from| pattern.find_at(haystack, from).map(|m| (m.start(), m.end())))
+            }
+            None => self.boundary.find(haystack, token),
         }
     }
 
-    pub fn process_path_component(&self, path: &Path) -> Option<String> {
+    fn reintroductions(&self) -> Vec<(&str, &str)> {
+        reintroductions([(self.token.as_str(), self.replacement.as_str())], &self.syntax, self.protect)
+    }
+
+    /// Whether `text` is one of the replacements, as written by an earlier run.
+    fn is_replacement(&self, text: &str) -> bool {
+        let cased = &self.case_replacements;
+        text == self.replacement
+            || [&cased.upper, &cased.lower, &cased.title, &cased.mixed]
+                .into_iter()
+                .any(|replacement| replacement.as_deref() == Some(text))
+    }
+
+    /// Replaces the token outside existing template syntax.
+    fn replace(&self, haystack: &str, token: &str, is_path: bool) -> Replaced {
+        self.checked.call_once(|| warn_reintroduced(&self.reintroductions()));
+        replace_outside_templates(
+            haystack,
+            &self.syntax,
+            self.protect,
+            |text| {
+                let matches = self.find(text, token, is_path);
+                let replaced = replace_matches(text, &matches, |matched| {
+                    LetterCase::of(matched)
+                        .and_then(|case| self.case_replacements.get(case))
+                        .unwrap_or(&self.replacement)
+                });
+                (replaced, matches.len())
+            },
+            |text| if self.is_replacement(text) { 0 } else { self.find(text, token, is_path).len() },
+        )
+    }
+}
+
+impl Templater for ExactTemplater {
+    fn process_content(&self, content: &str) -> Option<String> {
+        let replaced = self.replace(content, &self.token, false);
+        self.protected.set(self.protected.get() + replaced.protected);
+        let new_content = replaced.text?;
+        debug!("Content replacement: found {} occurrences", replaced.count);
+        Some(new_content)
+    }
+
+    fn take_protected(&self) -> usize {
+        self.protected.take()
+    }
+
+    fn process_path_component(&self, path: &Path) -> Option<String> {
         if let Some(file_name) = path.file_name() {
             if let Some(name_str) = file_name.to_str() {
-                if name_str.contains(&self.token) {
-                    let new_name = name_str.replace(&self.token, &self.replacement);
+                if let Some(new_name) = self.replace(name_str, &self.token, false).text {
                     debug!("Path replacement: '{}' -> '{}'", name_str, new_name);
                     return Some(new_name);
                 }
@@ -60,15 +380,14 @@ impl ExactTemplater {
         None
     }
 
-    pub fn process_full_path(&self, path: &Path) -> Option<PathBuf> {
+    fn process_full_path(&self, path: &Path) -> Option<PathBuf> {
         // Convert path to string for replacement
         if let Some(path_str) = path.to_str() {
             // Normalize path separators to forward slashes for consistent matching
             let normalized_path = path_str.replace('\\', "/");
             let normalized_token = self.token.replace('\\', "/");
             
-            if normalized_path.contains(&normalized_token) {
-                let new_path_str = normalized_path.replace(&normalized_token, &self.replacement);
+            if let Some(new_path_str) = self.replace(&normalized_path, &normalized_token, true).text {
                 debug!("Full path replacement: '{}' -> '{}'", path_str, new_path_str);
                 
                 // Convert back to PathBuf with proper separators for the current OS
@@ -79,140 +398,404 @@ impl ExactTemplater {
     }
 }
 
+impl MultiExactTemplater {
+    /// Replaces each token of `pairs` with its replacement; the first of duplicate tokens wins.
+    pub fn new<T, R>(pairs: impl IntoIterator<Item = (T, R)>, boundary: WordBoundary) -> Self
+    where
+        T: AsRef<str>,
+        R: Into<String>,
+    {
+        Self {
+            replacer: MultiReplacer::new(pairs).with_word_boundary(boundary),
+            syntax: Syntax::default(),
+            protect: true,
+            checked: Once::new(),
+            protected: Cell::new(0),
+        }
+    }
+
+    /// Whether text inside existing template syntax is left alone, which is the default.
+    pub fn with_protection(mut self, protect: bool) -> Self {
+        self.protect = protect;
+        self
+    }
+
+    /// Writes the replacements in `syntax` and leaves text in that syntax alone.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.replacer = self.replacer.map_replacements(|replacement| syntax.adopt(replacement));
+        self.syntax = syntax;
+        self
+    }
+
+    fn reintroductions(&self) -> Vec<(&str, &str)> {
+        reintroductions(self.replacer.pairs(), &self.syntax, self.protect)
+    }
+
+    /// Replaces the tokens outside existing template syntax.
+    fn replace(&self, haystack: &str) -> Replaced {
+        self.checked.call_once(|| warn_reintroduced(&self.reintroductions()));
+        replace_outside_templates(
+            haystack,
+            &self.syntax,
+            self.protect,
+            |text| self.replacer.replace_counted(text),
+            |text| count_protected(&self.replacer, text),
+        )
+    }
+}
+
+impl Templater for MultiExactTemplater {
+    fn process_content(&self, content: &str) -> Option<String> {
+        let replaced = self.replace(content);
+        self.protected.set(self.protected.get() + replaced.protected);
+        let new_content = replaced.text?;
+        debug!("Content replacement: found {} occurrences", replaced.count);
+        Some(new_content)
+    }
+
+    fn process_path_component(&self, path: &Path) -> Option<String> {
+        let name_str = path.file_name()?.to_str()?;
+        let new_name = self.replace(name_str).text?;
+        debug!("Path replacement: '{}' -> '{}'", name_str, new_name);
+        Some(new_name)
+    }
+
+    fn process_full_path(&self, path: &Path) -> Option<PathBuf> {
+        // Match against forward slashes so tokens can span segments on every platform
+        let normalized_path = path.to_str()?.replace('\\', "/");
+        let new_path_str = self.replace(&normalized_path).text?;
+        debug!("Full path replacement: '{}' -> '{}'", normalized_path, new_path_str);
+        Some(PathBuf::from(new_path_str))
+    }
+
+    fn take_protected(&self) -> usize {
+        self.protected.take()
+    }
+}
+
+impl RegexTemplater {
+    pub fn new(pattern: &str, replacement: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.to_string(),
+        })
+    }
+
+    /// Writes the replacement in `syntax`.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.replacement = syntax.adopt(&self.replacement);
+        self
+    }
+
+    fn replace(&self, input: &str) -> Option<String> {
+        if !self.pattern.is_match(input) {
+            return None;
+        }
+        let replaced = self.pattern.replace_all(input, self.replacement.as_str());
+        (replaced != input).then(|| replaced.into_owned())
+    }
+}
+
+impl Templater for RegexTemplater {
+    fn process_content(&self, content: &str) -> Option<String> {
+        self.replace(content)
+    }
+
+    fn process_path_component(&self, path: &Path) -> Option<String> {
+        let name_str = path.file_name()?.to_str()?;
+        let new_name = self.replace(name_str)?;
+        debug!("Regex path replacement: '{}' -> '{}'", name_str, new_name);
+        Some(new_name)
+    }
+
+    fn process_full_path(&self, path: &Path) -> Option<PathBuf> {
+        // Match against forward slashes so patterns can span segments on every platform
+        let normalized_path = path.to_str()?.replace('\\', "/");
+        let new_path_str = self.replace(&normalized_path)?;
+        debug!("Regex full path replacement: '{}' -> '{}'", normalized_path, new_path_str);
+        Some(PathBuf::from(new_path_str))
+    }
+}
+
 impl JinjaEscaper {
-    pub fn new() -> Result<Self, regex::Error> {
-        let jinja_pattern = Regex::new(r"\{\{\s*([^}]+)\s*\}\}")?;
-        Ok(Self { jinja_pattern })
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strategy(strategy: EscapeStrategy) -> Self {
+        Self {
+            strategy,
+            ..Self::default()
+        }
+    }
+
+    /// Escapes only text that collides with the delimiters of `syntax`.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
     }
 
     pub fn escape_content(&self, content: &str) -> Option<String> {
-        if self.jinja_pattern.is_match(content) {
-            let escaped = self.jinja_pattern.replace_all(content, |caps: &regex::Captures| {
-                let inner = caps.get(1).unwrap().as_str().trim();
-                format!("{{{{'{{'}}}}{{ {} }}}}", inner)
-            });
-            let count = self.jinja_pattern.find_iter(content).count();
-            debug!("Jinja escaping: found {} Jinja expressions", count);
-            Some(escaped.to_string())
-        } else {
-            None
+        let escaped = jinja::escape_with(content, self.strategy, &self.syntax)?;
+        debug!("Jinja escaping with strategy {:?}", self.strategy);
+        Some(escaped)
+    }
+
+    /// The dialects found in each file escaped so far, in the order the changes were applied.
+    pub fn take_reports(&self) -> Vec<DialectReport> {
+        self.reports.take()
+    }
+}
+
+impl Templater for JinjaEscaper {
+    fn process_content(&self, content: &str) -> Option<String> {
+        self.escape_content(content)
+    }
+
+    fn process_file_content(&self, relative: &Path, content: &str) -> Option<String> {
+        if !self.syntax.has_opener(content) {
+            return None;
+        }
+        // Lexed once, for both detecting the dialects and escaping
+        let segments = jinja::lex(content, &self.syntax);
+        let report = DialectReport::detect(relative, content, &segments, self.strategy);
+        let dialects: Vec<_> = report.dialects.iter().map(|dialect| dialect.name()).collect();
+        info!("Found {} in {:?}, escaping with {}", dialects.join(", "), relative, report.plan);
+        let escaped = match report.plan {
+            EscapePlan::WholeFile => jinja::escape_raw(content, &self.syntax),
+            EscapePlan::Strategy(strategy) => jinja::escape_lexed(content, &segments, strategy, &self.syntax),
+        };
+        self.pending.borrow_mut().insert(relative.to_path_buf(), report);
+        escaped
+    }
+
+    /// Escapes delimiters in a file or directory name inline, so that rendering the name
+    /// reproduces it, as for `{{cookiecutter.project_slug}}` or `{% raw %}.txt`.
+    fn process_path_component(&self, path: &Path) -> Option<String> {
+        let name = path.file_name()?.to_str()?;
+        let escaped = jinja::escape(name, &self.syntax)?;
+        debug!("Jinja escaping path: '{}' -> '{}'", name, escaped);
+        Some(escaped)
+    }
+
+    fn content_change_description(&self) -> &str {
+        "Jinja escaping"
+    }
+
+    fn content_applied(&self, relative: &Path) {
+        if let Some(report) = self.pending.borrow_mut().remove(relative) {
+            self.reports.borrow_mut().push(report);
         }
     }
 }
 
+impl JinjaUnescaper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reverses escapes written for the delimiters of `syntax`.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+}
+
+impl Templater for JinjaUnescaper {
+    fn process_content(&self, content: &str) -> Option<String> {
+        jinja::unescape(content, &self.syntax)
+    }
+
+    fn process_path_component(&self, path: &Path) -> Option<String> {
+        let name = path.file_name()?.to_str()?;
+        let unescaped = jinja::unescape(name, &self.syntax)?;
+        debug!("Jinja unescaping path: '{}' -> '{}'", name, unescaped);
+        Some(unescaped)
+    }
+
+    fn content_change_description(&self) -> &str {
+        "Jinja unescaping"
+    }
+}
+
 impl CaseShapeTemplater {
+    /// Maps the token to the replacement in every [`CaseShape`].
     pub fn new(token: &str, replacement: &str) -> Result<Self, anyhow::Error> {
-        // Validate that both token and replacement are compound words
-        Self::validate_compound_word(token, "token")?;
-        Self::validate_compound_word(replacement, "replacement")?;
-
-        let mut replacements = HashMap::new();
-        
-        // Generate all case shape variants
-        let cases = [
-            Case::Camel,      // camelCase
-            Case::Pascal,     // PascalCase  
-            Case::Kebab,      // kebab-case
-            Case::Snake,      // snake_case
-            Case::Train,      // Train-Case
-            Case::ScreamingSnake, // SCREAMING_SNAKE_CASE
-            Case::Cobol,      // COBOL-CASE
-        ];
-
-        for case in &cases {
-            let token_variant = token.to_case(*case);
-            
-            // Extract and convert the variable content from the replacement template
-            let replacement_variant = if replacement.contains("{{") && replacement.contains("}}") {
-                let jinja_pattern = Regex::new(r"\{\{\s*([^}]+)\s*\}\}").unwrap();
-                if let Some(caps) = jinja_pattern.captures(&replacement) {
-                    let inner_content = caps.get(1).unwrap().as_str().trim();
-                    let converted_inner = inner_content.to_case(*case);
-                    format!("{{{{ {} }}}}", converted_inner)
-                } else {
-                    replacement.to_case(*case)
-                }
-            } else {
-                replacement.to_case(*case)
-            };
-            
-            debug!("Case shape mapping: {} -> {}", token_variant, replacement_variant);
-            replacements.insert(token_variant, replacement_variant);
+        Self::with_options(token, replacement, &ShapeOptions::default())
+    }
+
+    /// Maps the token to the replacement in each of `shapes`, plus the forms as given.
+    pub fn with_shapes(token: &str, replacement: &str, shapes: &[CaseShape]) -> Result<Self, anyhow::Error> {
+        let options = ShapeOptions {
+            shapes: shapes.to_vec(),
+            ..ShapeOptions::default()
+        };
+        Self::with_options(token, replacement, &options)
+    }
+
+    /// Maps the token to the replacement in each shape selected by `options`.
+    ///
+    /// With [`ShapeOptions::filters`] set, every shape keeps the replacement's variable and
+    /// appends that shape's filter, so `{{ project_name }}` becomes
+    /// `{{ project_name | camel_case }}` for the camelCase form of the token.
+    pub fn with_options(token: &str, replacement: &str, options: &ShapeOptions) -> Result<Self, anyhow::Error> {
+        let template = ReplacementTemplate::parse(replacement);
+
+        let splitter = WordSplitter::new(&options.acronyms);
+        let token_words = splitter.split(token);
+        let replacement_words = splitter.split(replacement);
+        if token_words.is_empty() {
+            anyhow::bail!("The token '{}' has no words to build case shapes from", token);
+        }
+        if !template.has_expressions() {
+            if options.filters.is_some() {
+                anyhow::bail!(
+                    "Filter-style replacements need a Jinja expression such as '{{{{ project_name }}}}', got '{}'",
+                    replacement
+                );
+            }
+            if replacement_words.is_empty() {
+                anyhow::bail!("The replacement '{}' has no words to build case shapes from", replacement);
+            }
         }
 
-        // Also include the original forms
-        replacements.insert(token.to_string(), replacement.to_string());
+        // A single word only has lower, Title and UPPER forms, which these shapes write while
+        // keeping compound variables usable as identifiers
+        let shapes = match (&options.shapes[..], &token_words[..]) {
+            ([], [_]) => &SINGLE_WORD_SHAPES[..],
+            ([], _) => &CaseShape::ALL[..],
+            (shapes, _) => shapes,
+        };
+        let mut mappings: Vec<CaseShapeMapping> = Vec::new();
 
-        Ok(Self { replacements })
-    }
+        let add_mappings = |mappings: &mut Vec<CaseShapeMapping>, inflection: Option<Inflection>| {
+            let inflect = |words: &[String]| match inflection {
+                Some(inflection) => inflection.apply_to_last_word(words),
+                None => words.to_vec(),
+            };
+            let token_form = inflect(&token_words);
+            for &shape in shapes {
+                let token_variant = shape.join(&token_form, &splitter);
+                if mappings.iter().any(|mapping| mapping.original == token_variant) {
+                    continue;
+                }
 
-    fn validate_compound_word(word: &str, field_name: &str) -> Result<(), anyhow::Error> {
-        // Remove Jinja syntax for validation if present
-        let clean_word = if word.contains("{{") && word.contains("}}") {
-            // Extract content between {{ }}
-            let jinja_pattern = Regex::new(r"\{\{\s*([^}]+)\s*\}\}").unwrap();
-            if let Some(caps) = jinja_pattern.captures(word) {
-                caps.get(1).unwrap().as_str().trim()
-            } else {
-                word
+                // Convert the variables of the replacement template, or the replacement itself
+                let replacement_variant = match &options.filters {
+                    Some(filters) => {
+                        let inflection_filter = inflection.map(|inflection| filters.name(inflection));
+                        let shape_filters: Vec<_> = inflection_filter.into_iter().chain([filters.name(shape)]).collect();
+                        template.filter_variables(&shape_filters)
+                    }
+                    None if template.has_expressions() && shape.is_identifier() => template
+                        .rename_variables(|variable| shape.join(&inflect(&splitter.split(variable)), &splitter)),
+                    // Shapes joined by dots, slashes or spaces would no longer be variables, so
+                    // they refer to the snake_case variable through the shape's filter
+                    None if template.has_expressions() => {
+                        let snake = template.rename_variables(|variable| {
+                            CaseShape::Snake.join(&inflect(&splitter.split(variable)), &splitter)
+                        });
+                        ReplacementTemplate::parse(&snake).filter_variables(&[shape.filter()])
+                    }
+                    None => shape.join(&inflect(&replacement_words), &splitter),
+                };
+
+                debug!("Case shape mapping ({}): {} -> {}", shape, token_variant, replacement_variant);
+                mappings.push(CaseShapeMapping {
+                    shape: shape.name().to_string(),
+                    inflection,
+                    original: token_variant,
+                    replacement: replacement_variant,
+                });
             }
-        } else {
-            word
         };
+        add_mappings(&mut mappings, None);
 
-        // Check if word contains separators indicating compound nature
-        let has_separators = clean_word.contains('-') || 
-                           clean_word.contains('_') || 
-                           clean_word.chars().any(|c| c.is_uppercase());
-
-        if !has_separators {
-            anyhow::bail!(
-                "The {} '{}' does not appear to be a compound word. \
-                Compound words should contain separators like hyphens (-), underscores (_), \
-                or mixed case (e.g., 'example-name', 'project_name', 'ProjectName')",
-                field_name, word
-            );
+        // Also include the original forms. A renamed replacement as given takes precedence over a
+        // shape producing the same token, while a filtered one keeps that shape's filter.
+        if options.filters.is_none() {
+            mappings.retain(|mapping| mapping.original != token);
+        }
+        if !mappings.iter().any(|mapping| mapping.original == token) {
+            mappings.push(CaseShapeMapping {
+                shape: "original".to_string(),
+                inflection: None,
+                original: token.to_string(),
+                replacement: replacement.to_string(),
+            });
         }
 
-        Ok(())
+        // The replacement stands for the token as given, so the other number of its last word is
+        // mapped to an inflected variable or filter
+        if let Some(inflection) = Inflection::opposite_of(&token_words).filter(|_| options.inflect) {
+            add_mappings(&mut mappings, Some(inflection));
+        }
+
+        let replacer = MultiReplacer::new(mappings.iter().map(|m| (&m.original, m.replacement.clone())));
+        Ok(Self {
+            mappings,
+            replacer,
+            syntax: Syntax::default(),
+            protect: true,
+            checked: Once::new(),
+            protected: Cell::new(0),
+        })
     }
 
-    pub fn get_mappings(&self) -> Vec<CaseShapeMapping> {
-        self.replacements.iter()
-            .map(|(k, v)| CaseShapeMapping {
-                original: k.clone(),
-                replacement: v.clone(),
-            })
-            .collect()
-    }
-
-    pub fn process_content(&self, content: &str) -> Option<String> {
-        let mut modified_content = content.to_string();
-        let mut found_replacements = false;
-
-        // Sort by length (longest first) to avoid partial matches
-        let mut sorted_replacements: Vec<_> = self.replacements.iter().collect();
-        sorted_replacements.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
-
-        for (token, replacement) in sorted_replacements {
-            if modified_content.contains(token) {
-                modified_content = modified_content.replace(token, replacement);
-                found_replacements = true;
-                debug!("Case shape replacement: '{}' -> '{}'", token, replacement);
-            }
-        }
+    /// Whether text inside existing template syntax is left alone, which is the default.
+    pub fn with_protection(mut self, protect: bool) -> Self {
+        self.protect = protect;
+        self
+    }
 
-        if found_replacements {
-            Some(modified_content)
-        } else {
-            None
+    /// Writes the replacements in `syntax` and leaves text in that syntax alone.
+    ///
+    /// The replacement given to the constructor must use the standard syntax, so its variables
+    /// can be converted; [`Syntax::translate`] turns one written in `syntax` into it.
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        for mapping in &mut self.mappings {
+            mapping.replacement = syntax.adopt(&mapping.replacement);
         }
+        self.replacer = self.replacer.map_replacements(|replacement| syntax.adopt(replacement));
+        self.syntax = syntax;
+        self
+    }
+
+    pub fn get_mappings(&self) -> Vec<CaseShapeMapping> {
+        self.mappings.clone()
+    }
+
+    fn reintroductions(&self) -> Vec<(&str, &str)> {
+        let pairs = self.mappings.iter().map(|mapping| (mapping.original.as_str(), mapping.replacement.as_str()));
+        reintroductions(pairs, &self.syntax, self.protect)
     }
 
-    pub fn process_path_component(&self, path: &Path) -> Option<String> {
+    /// Replaces every shape outside existing template syntax.
+    fn replace(&self, haystack: &str) -> Replaced {
+        self.checked.call_once(|| warn_reintroduced(&self.reintroductions()));
+        replace_outside_templates(
+            haystack,
+            &self.syntax,
+            self.protect,
+            |text| self.replacer.replace_counted(text),
+            |text| count_protected(&self.replacer, text),
+        )
+    }
+}
+
+impl Templater for CaseShapeTemplater {
+    fn process_content(&self, content: &str) -> Option<String> {
+        let replaced = self.replace(content);
+        self.protected.set(self.protected.get() + replaced.protected);
+        let new_content = replaced.text?;
+        debug!("Case shape replacement: found {} occurrences", replaced.count);
+        Some(new_content)
+    }
+
+    fn process_path_component(&self, path: &Path) -> Option<String> {
         if let Some(file_name) = path.file_name() {
             if let Some(name_str) = file_name.to_str() {
-                if let Some(new_content) = self.process_content(name_str) {
+                if let Some(new_content) = self.replace(name_str).text {
                     if new_content != name_str {
                         debug!("Case shape path replacement: '{}' -> '{}'", name_str, new_content);
                         return Some(new_content);
@@ -223,13 +806,13 @@ impl CaseShapeTemplater {
         None
     }
 
-    pub fn process_full_path(&self, path: &Path) -> Option<PathBuf> {
+    fn process_full_path(&self, path: &Path) -> Option<PathBuf> {
         // Convert path to string for replacement
         if let Some(path_str) = path.to_str() {
             // Normalize path separators to forward slashes for consistent matching
             let normalized_path = path_str.replace('\\', "/");
             
-            if let Some(new_path_content) = self.process_content(&normalized_path) {
+            if let Some(new_path_content) = self.replace(&normalized_path).text {
                 if new_path_content != normalized_path {
                     debug!("Case shape full path replacement: '{}' -> '{}'", path_str, new_path_content);
                     // Convert back to PathBuf with proper separators for the current OS
@@ -239,11 +822,17 @@ impl CaseShapeTemplater {
         }
         None
     }
+
+    fn take_protected(&self) -> usize {
+        self.protected.take()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shapes::CaseFilters;
+    use crate::dialect::Dialect;
 
     #[test]
     fn test_content_replacement() {
@@ -290,9 +879,206 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_word_boundary_replacement() {
+        let templater = ExactTemplater::new("app", "{{ app }}").with_word_boundary(WordBoundary::Identifier);
+
+        assert_eq!(
+            templater.process_content("app = application(my_app, app-config, app)").unwrap(),
+            "{{ app }} = application(my_app, app-config, {{ app }})"
+        );
+        assert_eq!(
+            templater.process_full_path(Path::new("src/app/wrapper/app.rs")),
+            Some(PathBuf::from("src/{{ app }}/wrapper/{{ app }}.rs"))
+        );
+        assert_eq!(templater.process_path_component(Path::new("happy.txt")), None);
+    }
+
+    #[test]
+    fn test_case_insensitive_replacement() {
+        let templater = ExactTemplater::new("Acme Corp", "{{ company }}").with_ignore_case(CaseReplacements {
+            upper: Some("{{ company | upper }}".to_string()),
+            lower: Some("{{ company | lower }}".to_string()),
+            ..CaseReplacements::default()
+        });
+
+        assert_eq!(
+            templater.process_content("Acme Corp, ACME CORP, acme corp and AcMe corp").unwrap(),
+            "{{ company }}, {{ company | upper }}, {{ company | lower }} and {{ company }}"
+        );
+        assert_eq!(
+            templater.process_full_path(Path::new("ACME CORP/acme corp.txt")),
+            Some(PathBuf::from("{{ company | upper }}/{{ company | lower }}.txt"))
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_respects_word_boundary() {
+        let templater = ExactTemplater::new("app", "{{ app }}")
+            .with_word_boundary(WordBoundary::Unicode)
+            .with_ignore_case(CaseReplacements::default());
+
+        assert_eq!(
+            templater.process_content("App APP Application").unwrap(),
+            "{{ app }} {{ app }} Application"
+        );
+    }
+
+    #[test]
+    fn test_multi_exact_replacement() {
+        let templater = MultiExactTemplater::new(
+            [("Acme", "{{ company }}"), ("Acme Widgets", "{{ product }}"), ("company", "{{ noun }}")],
+            WordBoundary::None,
+        );
+
+        assert_eq!(
+            templater.process_content("Acme Widgets by Acme, a company").unwrap(),
+            "{{ product }} by {{ company }}, a {{ noun }}"
+        );
+        assert_eq!(
+            templater.process_full_path(Path::new("Acme/Acme Widgets.md")),
+            Some(PathBuf::from("{{ company }}/{{ product }}.md"))
+        );
+    }
+
+    #[test]
+    fn test_case_shape_does_not_rescan_replacements() {
+        // The replacement for `app-name` contains the snake_case form of the token
+        let templater = CaseShapeTemplater::new("app-name", "{{ app_name }}").unwrap();
+        assert_eq!(
+            templater.process_content("app-name app_name AppName").unwrap(),
+            "{{ app_name }} {{ app_name }} {{ AppName }}"
+        );
+    }
+
+    #[test]
+    fn test_existing_template_regions_are_protected() {
+        let content = "app {{ app }} {% if app %}app{% endif %} {{'{'}}{ app }} {% raw %}app{% endraw %}";
+        let expected = "{{ app }} {{ app }} {% if app %}{{ app }}{% endif %} {{'{'}}{ app }} {% raw %}app{% endraw %}";
+
+        let templater = ExactTemplater::new("app", "{{ app }}");
+        let once = templater.process_content(content).unwrap();
+        assert_eq!(once, expected);
+        assert_eq!(templater.process_content(&once), None);
+
+        let templater = MultiExactTemplater::new([("app", "{{ app }}")], WordBoundary::None);
+        assert_eq!(templater.process_content(content).unwrap(), expected);
+
+        let templater = CaseShapeTemplater::new("my-app", "{{ my-app }}").unwrap();
+        let once = templater.process_content("MyApp {{ MyApp }}").unwrap();
+        assert_eq!(once, "{{ MyApp }} {{ MyApp }}");
+        assert_eq!(templater.process_content(&once), None);
+        // Replacements written by an earlier run aren't reported as left alone
+        assert_eq!(templater.take_protected(), 0);
+    }
+
+    #[test]
+    fn test_protection_can_be_turned_off() {
+        let content = r#"let greeting = format!("{{acme}} {}", acme);"#;
+        let templater = ExactTemplater::new("acme", "{{ org }}");
+        assert_eq!(
+            templater.process_content(content).unwrap(),
+            r#"let greeting = format!("{{acme}} {}", {{ org }});"#
+        );
+        // The occurrence left inside the braces is reported rather than silently skipped
+        assert_eq!(templater.take_protected(), 1);
+        assert_eq!(templater.take_protected(), 0);
+        assert_eq!(templater.process_content("<div style={{color: acme}}/>"), None);
+        assert_eq!(templater.take_protected(), 1);
+        assert_eq!(
+            ExactTemplater::new("acme", "{{ org }}").with_protection(false).process_content(content).unwrap(),
+            r#"let greeting = format!("{{{{ org }}}} {}", {{ org }});"#
+        );
+
+        let templater = MultiExactTemplater::new([("acme", "{{ org }}")], WordBoundary::None).with_protection(false);
+        assert_eq!(
+            templater.process_content(content).unwrap(),
+            r#"let greeting = format!("{{{{ org }}}} {}", {{ org }});"#
+        );
+
+        let templater = CaseShapeTemplater::new("acme-corp", "{{ org }}").unwrap().with_protection(false);
+        assert_eq!(templater.process_content(r#"format!("{{AcmeCorp}}")"#).unwrap(), r#"format!("{{{{ Org }}}}")"#);
+        assert_eq!(templater.take_protected(), 0);
+    }
+
+    #[test]
+    fn test_reintroductions_use_the_configured_syntax() {
+        let templater = ExactTemplater::new("app", "[[ app ]]");
+        assert_eq!(templater.reintroductions(), vec![("app", "[[ app ]]")]);
+        let templater = templater.with_syntax("[[ ]] [% %]".parse().unwrap());
+        assert!(templater.reintroductions().is_empty());
+
+        let templater = ExactTemplater::new("app", "{{ app }}");
+        assert!(templater.reintroductions().is_empty());
+        assert_eq!(templater.with_protection(false).reintroductions(), vec![("app", "{{ app }}")]);
+
+        let templater = MultiExactTemplater::new([("app", "my-app"), ("org", "{{ org }}")], WordBoundary::None);
+        assert_eq!(templater.reintroductions(), vec![("app", "my-app")]);
+
+        let templater = CaseShapeTemplater::new("app", "{{ app }}").unwrap();
+        assert!(templater.reintroductions().is_empty());
+        let templater = CaseShapeTemplater::new("app", "{{ app }}").unwrap().with_syntax("<< >> <% %>".parse().unwrap());
+        assert!(templater.reintroductions().is_empty());
+    }
+
+    #[test]
+    fn test_custom_syntax() {
+        let syntax: Syntax = "[[ ]] [% %]".parse().unwrap();
+
+        let templater = ExactTemplater::new("app", "{{ app }}").with_syntax(syntax.clone());
+        assert_eq!(
+            templater.process_content("app {{ app }} [[ app ]] [% if app %]").unwrap(),
+            "[[ app ]] {{ [[ app ]] }} [[ app ]] [% if app %]"
+        );
+
+        let templater = MultiExactTemplater::new([("app", "[[ app ]]")], WordBoundary::None).with_syntax(syntax.clone());
+        assert_eq!(templater.process_content("app").unwrap(), "[[ app ]]");
+
+        let templater = CaseShapeTemplater::new("my-app", "{{ app_name }}").unwrap().with_syntax(syntax);
+        assert_eq!(
+            templater.process_content("MyApp my_app [[ MyApp ]]").unwrap(),
+            "[[ AppName ]] [[ app_name ]] [[ MyApp ]]"
+        );
+    }
+
+    #[test]
+    fn test_regex_capture_groups() {
+        let templater = RegexTemplater::new(r"com\.acme\.(\w+)", "{{ group_id }}.$1").unwrap();
+
+        assert_eq!(
+            templater.process_content("import com.acme.widgets.Widget;").unwrap(),
+            "import {{ group_id }}.widgets.Widget;"
+        );
+        assert_eq!(templater.process_content("import org.other.Thing;"), None);
+
+        let versions = RegexTemplater::new(r"(?P<major>\d+)\.\d+\.\d+", "${major}.x").unwrap();
+        assert_eq!(versions.process_content("version = \"1.4.2\"").unwrap(), "version = \"1.x\"");
+    }
+
+    #[test]
+    fn test_regex_paths() {
+        let templater = RegexTemplater::new(r"com/acme/(\w+)", "{{ package }}/$1").unwrap();
+        assert_eq!(
+            templater.process_full_path(Path::new("src/com/acme/widgets/App.java")),
+            Some(PathBuf::from("src/{{ package }}/widgets/App.java"))
+        );
+
+        let templater = RegexTemplater::new(r"^acme-(.*)\.rs$", "{{ name }}-$1.rs").unwrap();
+        assert_eq!(
+            templater.process_path_component(Path::new("src/acme-core.rs")),
+            Some("{{ name }}-core.rs".to_string())
+        );
+        assert_eq!(templater.process_path_component(Path::new("src/core.rs")), None);
+    }
+
+    #[test]
+    fn test_invalid_regex_is_reported() {
+        assert!(RegexTemplater::new("(unclosed", "x").is_err());
+    }
+
     #[test]
     fn test_jinja_escaping() {
-        let escaper = JinjaEscaper::new().unwrap();
+        let escaper = JinjaEscaper::new();
         
         let content = "This {{ project-name }} has {{ some-value }} and {{another-var}}.";
         let result = escaper.escape_content(content);
@@ -300,13 +1086,13 @@ mod tests {
         assert!(result.is_some());
         assert_eq!(
             result.unwrap(),
-            "This {{'{'}}{ project-name }} has {{'{'}}{ some-value }} and {{'{'}}{ another-var }}."
+            "This {{'{'}}{ project-name }} has {{'{'}}{ some-value }} and {{'{'}}{another-var}}."
         );
     }
 
     #[test]
     fn test_no_jinja_escaping() {
-        let escaper = JinjaEscaper::new().unwrap();
+        let escaper = JinjaEscaper::new();
         
         let content = "This content has no Jinja syntax to escape.";
         let result = escaper.escape_content(content);
@@ -316,7 +1102,7 @@ mod tests {
 
     #[test]
     fn test_jinja_escaping_with_spaces() {
-        let escaper = JinjaEscaper::new().unwrap();
+        let escaper = JinjaEscaper::new();
         
         let content = "{{ project-name }} and {{  spaced-var  }} should both be escaped.";
         let result = escaper.escape_content(content);
@@ -324,13 +1110,13 @@ mod tests {
         assert!(result.is_some());
         assert_eq!(
             result.unwrap(),
-            "{{'{'}}{ project-name }} and {{'{'}}{ spaced-var }} should both be escaped."
+            "{{'{'}}{ project-name }} and {{'{'}}{  spaced-var  }} should both be escaped."
         );
     }
 
     #[test]
     fn test_jinja_escaping_closing_braces_fix() {
-        let escaper = JinjaEscaper::new().unwrap();
+        let escaper = JinjaEscaper::new();
         
         // Test the specific case mentioned in the bug report
         let content = "{{ example }}";
@@ -353,14 +1139,100 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_jinja_escaping_statements_comments_and_whitespace_control() {
+        let escaper = JinjaEscaper::with_strategy(EscapeStrategy::Inline);
+
+        let content = "{%- for item in items -%}\n  {{- {'id': item.id} -}}\n{%- endfor %}{# note #}";
+        assert_eq!(
+            escaper.escape_content(content).unwrap(),
+            "{{'{'}}%- for item in items -%}\n  {{'{'}}{- {'id': item.id} -}}\n{{'{'}}%- endfor %}{{'{'}}# note #}"
+        );
+    }
+
+    #[test]
+    fn test_template_files_are_wrapped_in_raw_blocks() {
+        let content = "{{ a }}\n";
+        assert_eq!(
+            JinjaEscaper::new().process_file_content(Path::new("chart/values.yaml.J2"), content).unwrap(),
+            "{% raw %}{{ a }}\n{% endraw %}"
+        );
+        assert_eq!(
+            JinjaEscaper::new().process_file_content(Path::new("values.yaml"), content).unwrap(),
+            "{{'{'}}{ a }}\n"
+        );
+        assert_eq!(
+            JinjaEscaper::with_strategy(EscapeStrategy::Inline)
+                .process_file_content(Path::new("page.hbs"), content)
+                .unwrap(),
+            "{{'{'}}{ a }}\n"
+        );
+    }
+
+    #[test]
+    fn test_foreign_dialects_choose_the_escape_plan() {
+        let escaper = JinjaEscaper::new();
+        assert_eq!(
+            escaper.process_file_content(Path::new("chart/templates/service.yaml"), "port: {{ .Values.port }}\n").unwrap(),
+            "{% raw %}port: {{ .Values.port }}\n{% endraw %}"
+        );
+        assert_eq!(
+            escaper.process_file_content(Path::new("ci.yml"), "a: 1\nsha: ${{ github.sha }}\n").unwrap(),
+            "a: 1\n{% raw %}sha: ${{ github.sha }}{% endraw %}\n"
+        );
+        assert_eq!(escaper.process_file_content(Path::new("plain.txt"), "nothing"), None);
+        assert!(escaper.take_reports().is_empty());
+        escaper.content_applied(Path::new("chart/templates/service.yaml"));
+        escaper.content_applied(Path::new("ci.yml"));
+        escaper.content_applied(Path::new("plain.txt"));
+
+        let reports = escaper.take_reports();
+        let found: Vec<_> = reports.iter().map(|report| (report.path.to_str().unwrap(), report.dialects.clone())).collect();
+        assert_eq!(
+            found,
+            [("chart/templates/service.yaml", vec![Dialect::GoTemplate]), ("ci.yml", vec![Dialect::GitHubActions])]
+        );
+        assert!(escaper.take_reports().is_empty());
+    }
+
+    #[test]
+    fn test_jinja_escaping_paths() {
+        let escaper = JinjaEscaper::new();
+        assert_eq!(
+            escaper.process_path_component(Path::new("src/{{cookiecutter.project_slug}}")).unwrap(),
+            "{{'{'}}{cookiecutter.project_slug}}"
+        );
+        assert_eq!(
+            escaper.process_full_path(Path::new("{% raw %}/{{ a }}.txt/plain")).unwrap(),
+            PathBuf::from("{{'{'}}% raw %}/{{'{'}}{ a }}.txt/plain")
+        );
+        assert_eq!(escaper.process_path_component(Path::new("plain.txt")), None);
+    }
+
+    #[test]
+    fn test_jinja_unescaping_reverses_escaping() {
+        let escaper = JinjaEscaper::new();
+        let unescaper = JinjaUnescaper::new();
+        let content = "image: {{ .Values.image }}\nname: {{ name }}\n";
+        for path in ["chart/templates/a.yaml", "README.md", "page.j2"] {
+            let escaped = escaper.process_file_content(Path::new(path), content).unwrap();
+            assert_eq!(unescaper.process_file_content(Path::new(path), &escaped).unwrap(), content);
+        }
+
+        let name = Path::new("{{cookiecutter.project_slug}}");
+        let escaped = escaper.process_path_component(name).unwrap();
+        assert_eq!(unescaper.process_path_component(Path::new(&escaped)).unwrap(), name.to_str().unwrap());
+        assert_eq!(unescaper.process_content("{{ name }}"), None);
+    }
+
     #[test]
     fn test_case_shape_templater_creation() {
         let templater = CaseShapeTemplater::new("example-name", "{{ project-name }}").unwrap();
         let mappings = templater.get_mappings();
         
         
-        // Should have 7 case variants (original is same as kebab-case so gets deduplicated)
-        assert_eq!(mappings.len(), 7);
+        // Should have 14 case variants (original is same as kebab-case so gets deduplicated)
+        assert_eq!(mappings.len(), 14);
         
         // Check some specific mappings
         let mapping_map: std::collections::HashMap<String, String> = 
@@ -372,6 +1244,118 @@ mod tests {
         assert_eq!(mapping_map.get("example-name"), Some(&"{{ project-name }}".to_string()));
     }
 
+    #[test]
+    fn test_case_shape_extended_shapes() {
+        let templater = CaseShapeTemplater::new("example-name", "{{ project-name }}").unwrap();
+
+        let content = "example.name examplename EXAMPLENAME Example Name. Example name. The example name.";
+        assert_eq!(
+            templater.process_content(content).unwrap(),
+            "{{ project_name | dot_case }} {{ projectname }} {{ PROJECTNAME }} {{ project_name | title_case }}. \
+             {{ project_name | sentence_case }}. The {{ project_name | lower_case }}."
+        );
+        assert_eq!(
+            templater.process_full_path(Path::new("src/example/name/Main.java")),
+            Some(PathBuf::from("src/{{ project_name | directory_case }}/Main.java"))
+        );
+    }
+
+    #[test]
+    fn test_case_shape_subset() {
+        let templater =
+            CaseShapeTemplater::with_shapes("example-name", "{{ project-name }}", &[CaseShape::Pascal]).unwrap();
+
+        let shapes: Vec<_> = templater.get_mappings().into_iter().map(|m| m.shape).collect();
+        assert_eq!(shapes, ["pascal", "original"]);
+        assert_eq!(
+            templater.process_content("ExampleName example_name example-name").unwrap(),
+            "{{ ProjectName }} example_name {{ project-name }}"
+        );
+    }
+
+    #[test]
+    fn test_case_shape_filter_style() {
+        let options = ShapeOptions {
+            filters: Some(CaseFilters::default().with_name(CaseShape::Pascal, "class_case")),
+            ..ShapeOptions::default()
+        };
+        let templater = CaseShapeTemplater::with_options("example-name", "{{ name }}", &options).unwrap();
+
+        assert_eq!(
+            templater.process_content("final ExampleName exampleName = EXAMPLE_NAME; // example-name").unwrap(),
+            "final {{ name | class_case }} {{ name | camel_case }} = {{ name | constant_case }}; \
+             // {{ name | kebab_case }}"
+        );
+        let shapes: Vec<_> = templater.get_mappings().into_iter().map(|m| m.shape).collect();
+        assert!(!shapes.contains(&"original".to_string()));
+
+        assert!(CaseShapeTemplater::with_options("example-name", "project", &options).is_err());
+    }
+
+    #[test]
+    fn test_case_shape_inflections() {
+        let options = ShapeOptions {
+            inflect: true,
+            ..ShapeOptions::default()
+        };
+        let templater = CaseShapeTemplater::with_options("order-item", "{{ line-item }}", &options).unwrap();
+        assert_eq!(
+            templater.process_content("List<OrderItem> orderItems; ORDER_ITEMS order-item").unwrap(),
+            "List<{{ LineItem }}> {{ lineItems }}; {{ LINE_ITEMS }} {{ line-item }}"
+        );
+
+        let options = ShapeOptions {
+            filters: Some(CaseFilters::default()),
+            inflect: true,
+            ..ShapeOptions::default()
+        };
+        let templater = CaseShapeTemplater::with_options("order_categories", "{{ name }}", &options).unwrap();
+        assert_eq!(
+            templater.process_content("OrderCategory order_categories").unwrap(),
+            "{{ name | singularize | pascal_case }} {{ name | snake_case }}"
+        );
+        let inflections: Vec<_> = templater.get_mappings().into_iter().map(|m| m.inflection).collect();
+        assert_eq!(inflections.iter().filter(|i| **i == Some(Inflection::Singular)).count(), 14);
+    }
+
+    #[test]
+    fn test_case_shape_acronyms() {
+        let options = ShapeOptions {
+            acronyms: vec!["OAuth".to_string(), "ID".to_string()],
+            ..ShapeOptions::default()
+        };
+        let templater = CaseShapeTemplater::with_options("oauth2-client-id", "{{ auth-client-id }}", &options).unwrap();
+        assert_eq!(
+            templater.process_content("OAuth2ClientID oauth2ClientID OAUTH2_CLIENT_ID").unwrap(),
+            "{{ AuthClientID }} {{ authClientID }} {{ AUTH_CLIENT_ID }}"
+        );
+
+        let templater = CaseShapeTemplater::new("HTTPServer", "{{ web-server }}").unwrap();
+        assert_eq!(
+            templater.process_content("http_server HttpServer").unwrap(),
+            "{{ web_server }} {{ WebServer }}"
+        );
+    }
+
+    #[test]
+    fn test_case_shape_replacement_templates() {
+        let templater = CaseShapeTemplater::new("acme-widgets", "{{ org }}-{{ name | upper }}").unwrap();
+        assert_eq!(
+            templater.process_content("AcmeWidgets acme_widgets").unwrap(),
+            "{{ Org }}-{{ Name | upper }} {{ org }}-{{ name | upper }}"
+        );
+
+        let options = ShapeOptions {
+            filters: Some(CaseFilters::default()),
+            ..ShapeOptions::default()
+        };
+        let templater = CaseShapeTemplater::with_options("acme-widgets", "com.{{ org }}.{{ name | trim }}", &options).unwrap();
+        assert_eq!(
+            templater.process_content("AcmeWidgets").unwrap(),
+            "com.{{ org | pascal_case }}.{{ name | pascal_case | trim }}"
+        );
+    }
+
     #[test]
     fn test_case_shape_content_replacement() {
         let templater = CaseShapeTemplater::new("example-name", "{{ project-name }}").unwrap();
@@ -389,13 +1373,31 @@ mod tests {
 
     #[test]
     fn test_case_shape_validation_failure() {
-        // Should fail with single word
-        let result = CaseShapeTemplater::new("example", "{{ project }}");
-        assert!(result.is_err());
-        
-        // Should fail with no separators
-        let result = CaseShapeTemplater::new("exampleproject", "{{ projectname }}");
-        assert!(result.is_err());
+        // Should fail without any words
+        assert!(CaseShapeTemplater::new("--", "{{ project }}").is_err());
+        assert!(CaseShapeTemplater::new("example", "__").is_err());
+    }
+
+    #[test]
+    fn test_case_shape_single_word() {
+        let templater = CaseShapeTemplater::new("widget", "{{ part_name }}").unwrap();
+        let shapes: Vec<_> = templater.get_mappings().into_iter().map(|m| m.shape).collect();
+        assert_eq!(shapes, ["pascal", "screaming-snake", "original"]);
+        assert_eq!(
+            templater.process_content("Widget widget = WIDGETS.get(widget_id);").unwrap(),
+            "{{ PartName }} {{ part_name }} = {{ PART_NAME }}S.get({{ part_name }}_id);"
+        );
+
+        let options = ShapeOptions {
+            filters: Some(CaseFilters::default()),
+            inflect: true,
+            ..ShapeOptions::default()
+        };
+        let templater = CaseShapeTemplater::with_options("widget", "{{ name }}", &options).unwrap();
+        assert_eq!(
+            templater.process_content("Widgets widget WIDGET").unwrap(),
+            "{{ name | pluralize | pascal_case }} {{ name | snake_case }} {{ name | constant_case }}"
+        );
     }
 
     #[test]
@@ -0,0 +1,313 @@
+use anyhow::Result;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// Number of leading bytes inspected when sniffing for binary content.
+const SNIFF_LEN: usize = 8192;
+
+/// Signatures of common binary formats that may otherwise decode as text.
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"%PDF-", "PDF document"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"PK\x05\x06", "ZIP archive"),
+    (b"\x1f\x8b", "gzip archive"),
+    (b"BZh", "bzip2 archive"),
+    (b"\xfd7zXZ\x00", "xz archive"),
+    (b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (b"Rar!\x1a\x07", "RAR archive"),
+    (b"\x7fELF", "ELF binary"),
+    (b"\xca\xfe\xba\xbe", "Java class or Mach-O binary"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O binary"),
+    (b"\xce\xfa\xed\xfe", "Mach-O binary"),
+    (b"MZ", "Windows executable"),
+    (b"\x00asm", "WebAssembly module"),
+    (b"SQLite format 3\x00", "SQLite database"),
+    (b"wOFF", "WOFF font"),
+    (b"wOF2", "WOFF2 font"),
+    (b"RIFF", "RIFF media"),
+    (b"OggS", "Ogg media"),
+    (b"ID3", "MP3 audio"),
+];
+
+/// What `.gitattributes` says about a file, if anything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextHint {
+    /// `Some(true)` for `binary`/`-text`, `Some(false)` for `text`, `None` to sniff.
+    pub binary: Option<bool>,
+    /// Encoding from `working-tree-encoding`.
+    pub encoding: Option<&'static Encoding>,
+}
+
+/// Line break convention of a text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Only `\n` line breaks, or no line breaks at all.
+    Lf,
+    /// Only `\r\n` line breaks.
+    CrLf,
+    /// A mix of both, which is left exactly as found.
+    Mixed,
+}
+
+/// A decoded text file, remembering how to write it back byte-compatibly.
+///
+/// CRLF files are normalized to `\n` in `content` so templaters and diffs only ever see
+/// one convention; [`TextFile::encode`] restores the original line endings.
+#[derive(Debug)]
+pub struct TextFile {
+    pub content: String,
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+/// Outcome of inspecting a file's bytes.
+#[derive(Debug)]
+pub enum FileKind {
+    Text(TextFile),
+    Binary(&'static str),
+}
+
+/// Classifies `bytes` as text or binary and decodes text files.
+///
+/// Byte order marks and `.gitattributes` hints take precedence, then known magic numbers and
+/// NUL bytes mark binaries. UTF-8 is tried before `fallback`, which handles legacy single-byte
+/// encodings such as Windows-1252.
+pub fn decode(bytes: &[u8], hint: TextHint, fallback: &'static Encoding) -> FileKind {
+    if hint.binary == Some(true) {
+        return FileKind::Binary("marked binary in .gitattributes");
+    }
+
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        return decode_with(&bytes[bom_len..], encoding, true)
+            .unwrap_or(FileKind::Binary("malformed text after byte order mark"));
+    }
+
+    if let Some(encoding) = hint.encoding {
+        return decode_with(bytes, encoding, false)
+            .unwrap_or(FileKind::Binary("malformed working-tree-encoding text"));
+    }
+
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+    let forced_text = hint.binary == Some(false);
+    if !forced_text {
+        if let Some((_, format)) = MAGIC_NUMBERS.iter().find(|(magic, _)| sample.starts_with(magic)) {
+            return FileKind::Binary(format);
+        }
+    }
+
+    if let Some(encoding) = sniff_utf16(sample) {
+        if let Some(text) = decode_with(bytes, encoding, false) {
+            return text;
+        }
+    }
+
+    if !forced_text && sample.contains(&0) {
+        return FileKind::Binary("contains NUL bytes");
+    }
+
+    if let Some(text) = decode_with(bytes, UTF_8, false) {
+        return text;
+    }
+
+    if !forced_text && looks_like_control_data(sample) {
+        return FileKind::Binary("mostly control characters");
+    }
+
+    decode_with(bytes, fallback, false).unwrap_or(FileKind::Binary("not valid text in any known encoding"))
+}
+
+impl TextFile {
+    /// Encodes `content` in this file's original encoding, restoring its byte order mark
+    /// and line ending convention.
+    pub fn encode(&self, content: &str) -> Result<Vec<u8>> {
+        let content = match self.line_ending {
+            LineEnding::Lf => content.replace("\r\n", "\n"),
+            LineEnding::CrLf => content.replace("\r\n", "\n").replace('\n', "\r\n"),
+            LineEnding::Mixed => content.to_string(),
+        };
+        let content = content.as_str();
+
+        let mut bytes = Vec::with_capacity(content.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let little_endian = self.encoding == UTF_16LE;
+            if self.bom {
+                bytes.extend_from_slice(if little_endian { b"\xff\xfe" } else { b"\xfe\xff" });
+            }
+            for unit in content.encode_utf16() {
+                let pair = if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() };
+                bytes.extend_from_slice(&pair);
+            }
+            return Ok(bytes);
+        }
+
+        if self.bom && self.encoding == UTF_8 {
+            bytes.extend_from_slice(b"\xef\xbb\xbf");
+        }
+        let (encoded, _, had_errors) = self.encoding.encode(content);
+        if had_errors {
+            anyhow::bail!("New content cannot be represented in {}", self.encoding.name());
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+fn decode_with(bytes: &[u8], encoding: &'static Encoding, bom: bool) -> Option<FileKind> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|content| {
+            let line_ending = detect_line_ending(&content);
+            let content = match line_ending {
+                LineEnding::CrLf => content.replace("\r\n", "\n"),
+                _ => content.into_owned(),
+            };
+            FileKind::Text(TextFile {
+                content,
+                encoding,
+                bom,
+                line_ending,
+            })
+        })
+}
+
+fn detect_line_ending(content: &str) -> LineEnding {
+    let line_feeds = content.matches('\n').count();
+    let crlfs = content.matches("\r\n").count();
+    if crlfs == 0 {
+        LineEnding::Lf
+    } else if crlfs == line_feeds {
+        LineEnding::CrLf
+    } else {
+        LineEnding::Mixed
+    }
+}
+
+/// Detects BOM-less UTF-16 from the NUL bytes ASCII-range characters leave in every other byte.
+fn sniff_utf16(sample: &[u8]) -> Option<&'static Encoding> {
+    if sample.len() < 4 || !sample.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_nuls = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_nuls = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+
+    let mostly = |count: usize| count * 10 >= pairs * 9;
+    let rarely = |count: usize| count * 10 <= pairs;
+    if mostly(odd_nuls) && rarely(even_nuls) {
+        Some(UTF_16LE)
+    } else if mostly(even_nuls) && rarely(odd_nuls) {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+fn looks_like_control_data(sample: &[u8]) -> bool {
+    if sample.is_empty() {
+        return false;
+    }
+    let control = sample
+        .iter()
+        .filter(|b| (**b < 0x20 && !matches!(**b, b'\t' | b'\n' | b'\r' | 0x0c)) || **b == 0x7f)
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Resolves an encoding label such as `latin1` or `windows-1252`, defaulting to Windows-1252.
+pub fn fallback_encoding(label: Option<&str>) -> Result<&'static Encoding> {
+    match label {
+        None => Ok(WINDOWS_1252),
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| anyhow::anyhow!("Unknown text encoding: {}", label)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(bytes: &[u8]) -> TextFile {
+        match decode(bytes, TextHint::default(), WINDOWS_1252) {
+            FileKind::Text(file) => file,
+            FileKind::Binary(reason) => panic!("Expected text, got binary: {}", reason),
+        }
+    }
+
+    fn is_binary(bytes: &[u8], hint: TextHint) -> bool {
+        matches!(decode(bytes, hint, WINDOWS_1252), FileKind::Binary(_))
+    }
+
+    #[test]
+    fn test_utf8_round_trip() {
+        let file = text("héllo wörld".as_bytes());
+        assert_eq!(file.encoding, UTF_8);
+        assert!(!file.bom);
+        assert_eq!(file.encode(&file.content).unwrap(), "héllo wörld".as_bytes());
+    }
+
+    #[test]
+    fn test_latin1_is_decoded_as_windows_1252() {
+        let bytes = b"caf\xe9 acme";
+        let file = text(bytes);
+        assert_eq!(file.content, "café acme");
+        assert_eq!(file.encoding, WINDOWS_1252);
+        assert_eq!(file.encode("café {{ org }}").unwrap(), b"caf\xe9 {{ org }}");
+    }
+
+    #[test]
+    fn test_utf16_with_and_without_bom() {
+        let with_bom = b"\xff\xfea\x00c\x00m\x00e\x00";
+        let file = text(with_bom);
+        assert_eq!(file.content, "acme");
+        assert!(file.bom);
+        assert_eq!(file.encode(&file.content).unwrap(), with_bom);
+
+        let without_bom = b"\x00a\x00c\x00m\x00e";
+        let file = text(without_bom);
+        assert_eq!(file.content, "acme");
+        assert_eq!(file.encoding, UTF_16BE);
+        assert_eq!(file.encode("x").unwrap(), b"\x00x");
+    }
+
+    #[test]
+    fn test_line_endings_are_preserved() {
+        let file = text(b"\xef\xbb\xbfone\r\ntwo\r\n");
+        assert!(file.bom);
+        assert_eq!(file.line_ending, LineEnding::CrLf);
+        assert_eq!(file.content, "one\ntwo\n");
+        assert_eq!(file.encode("one\nmore\ntwo\n").unwrap(), b"\xef\xbb\xbfone\r\nmore\r\ntwo\r\n");
+
+        let file = text(b"one\ntwo\n");
+        assert_eq!(file.encode("one\r\ntwo\n").unwrap(), b"one\ntwo\n");
+
+        let file = text(b"one\r\ntwo\n");
+        assert_eq!(file.line_ending, LineEnding::Mixed);
+        assert_eq!(file.encode(&file.content).unwrap(), b"one\r\ntwo\n");
+    }
+
+    #[test]
+    fn test_binary_detection() {
+        assert!(is_binary(b"\x89PNG\r\n\x1a\nrest", TextHint::default()));
+        assert!(is_binary(b"PK\x03\x04plain ascii after the header", TextHint::default()));
+        assert!(is_binary(b"text\x00with nul", TextHint::default()));
+        assert!(is_binary(b"plain text", TextHint { binary: Some(true), encoding: None }));
+        assert!(!is_binary(b"MZ is a valid ticker", TextHint { binary: Some(false), encoding: None }));
+    }
+
+    #[test]
+    fn test_unrepresentable_replacement_is_an_error() {
+        let file = text(b"caf\xe9");
+        assert!(file.encode("caf\u{e9} \u{2603}").is_err());
+    }
+
+    #[test]
+    fn test_fallback_encoding_labels() {
+        assert_eq!(fallback_encoding(None).unwrap(), WINDOWS_1252);
+        assert_eq!(fallback_encoding(Some("shift_jis")).unwrap().name(), "Shift_JIS");
+        assert!(fallback_encoding(Some("klingon")).is_err());
+    }
+}
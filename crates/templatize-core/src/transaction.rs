@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// A single filesystem change made during a run.
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// `path` was overwritten; `original` holds its previous contents.
+    Write { path: PathBuf, original: Vec<u8> },
+    /// `from` was renamed to `to`.
+    Rename { from: PathBuf, to: PathBuf },
+    /// An empty directory was created at `path`.
+    CreateDir { path: PathBuf },
+    /// The empty directory at `path` was removed.
+    RemoveDir { path: PathBuf },
+}
+
+/// The change set of one run, applied eagerly and undone as a unit on failure.
+///
+/// Every mutation goes through the transaction so that [`Transaction::rollback`] can restore
+/// the tree exactly, leaving a project either fully transformed or untouched.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    changes: Vec<Change>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Changes applied so far, oldest first.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Atomically replaces the contents of `path`, remembering the original contents.
+    ///
+    /// Refuses symbolic links, which the rename into place would turn into regular files.
+    pub fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
+        if fs::symlink_metadata(path)?.file_type().is_symlink() {
+            anyhow::bail!("Refusing to replace symbolic link {:?} with a regular file", path);
+        }
+        let original = fs::read(path)?;
+        write_atomic(path, contents)?;
+        self.changes.push(Change::Write {
+            path: path.to_path_buf(),
+            original,
+        });
+        Ok(())
+    }
+
+    pub fn rename(&mut self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to)?;
+        self.changes.push(Change::Rename {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Creates `path` and any missing parents, recording each directory created.
+    pub fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        let missing: Vec<_> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+        for dir in missing.into_iter().rev() {
+            fs::create_dir(dir)?;
+            self.changes.push(Change::CreateDir {
+                path: dir.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn remove_dir(&mut self, path: &Path) -> Result<()> {
+        fs::remove_dir(path)?;
+        self.changes.push(Change::RemoveDir {
+            path: path.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Accepts every change, returning the change set.
+    pub fn commit(self) -> Vec<Change> {
+        self.changes
+    }
+
+    /// Undoes every change in reverse order.
+    ///
+    /// Keeps going when a step fails so as much as possible is restored, then reports the
+    /// first failure.
+    pub fn rollback(self) -> Result<()> {
+        let mut first_error = None;
+        for change in self.changes.into_iter().rev() {
+            debug!("Rolling back: {:?}", change);
+            let outcome = match &change {
+                Change::Write { path, original } => write_atomic(path, original),
+                Change::Rename { from, to } => fs::rename(to, from).map_err(Into::into),
+                Change::CreateDir { path } => fs::remove_dir(path).map_err(Into::into),
+                Change::RemoveDir { path } => fs::create_dir(path).map_err(Into::into),
+            };
+            if let Err(e) = outcome {
+                warn!("Failed to roll back {:?}: {}", change, e);
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e.context("Rollback was incomplete")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Replaces `path` by writing a sibling temporary file and renaming it into place.
+///
+/// Readers never observe a partially written file, and the original permissions (such as
+/// the executable bit) carry over to the new file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let permissions = fs::metadata(path).ok().map(|metadata| metadata.permissions());
+
+    let mut temp_name = OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(format!(".templatize-{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let written = (|| -> Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&temp_path, permissions)?;
+        }
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    })();
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written.with_context(|| format!("Failed to write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_restores_everything() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.txt");
+        let empty = temp.path().join("empty");
+        fs::write(&file, "original").unwrap();
+        fs::create_dir(&empty).unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.write(&file, b"changed").unwrap();
+        transaction.create_dir_all(&temp.path().join("x/y")).unwrap();
+        transaction.rename(&file, &temp.path().join("x/y/b.txt")).unwrap();
+        transaction.remove_dir(&empty).unwrap();
+        assert_eq!(transaction.changes().len(), 5);
+
+        transaction.rollback().unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "original");
+        assert!(empty.is_dir());
+        assert!(!temp.path().join("x").exists());
+    }
+
+    #[test]
+    fn test_commit_keeps_changes() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.txt");
+        fs::write(&file, "original").unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.write(&file, b"changed").unwrap();
+        let changes = transaction.commit();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "changed");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let script = temp.path().join("mvnw");
+        fs::write(&script, "#!/bin/sh").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+
+        write_atomic(&script, b"#!/bin/bash").unwrap();
+
+        assert_eq!(fs::read_to_string(&script).unwrap(), "#!/bin/bash");
+        assert_eq!(fs::metadata(&script).unwrap().permissions().mode() & 0o777, 0o750);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,991 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use encoding_rs::Encoding;
+use ignore::WalkBuilder;
+use tracing::{debug, info, warn};
+
+use crate::attributes::GitAttributes;
+use crate::filter::PathFilter;
+use crate::journal::{Journal, JOURNAL_DIRECTORY};
+use crate::templater::{TemplateOptions, Templater};
+use crate::text::{self, FileKind};
+use crate::transaction::{Change, Transaction};
+use crate::TemplatizeResult;
+
+/// Project-specific ignore file, using `.gitignore` syntax.
+pub const IGNORE_FILE_NAME: &str = ".templatizeignore";
+
+/// Version control metadata directories that are never walked, even with ignores disabled.
+pub const VCS_DIRECTORIES: &[&str] = &[".git", ".hg", ".svn", ".bzr", ".jj", "_darcs", "CVS"];
+
+type ContentCallback<'a> = dyn Fn(&Path, &str, &str, &str) -> Result<bool> + 'a;
+type PathCallback<'a> = dyn Fn(&Path, &Path, &str) -> Result<bool> + 'a;
+
+/// Applies `templater` to every file beneath `target`, or to `target` itself if it is a file.
+///
+/// Entries matched by `.gitignore`, global git excludes or [`IGNORE_FILE_NAME`] are skipped
+/// unless [`TemplateOptions::no_ignore`] is set; [`VCS_DIRECTORIES`] are always skipped.
+/// Contents are rewritten first. Paths are then mapped through
+/// [`Templater::process_full_path`] relative to `target`, so replacements spanning several
+/// segments (such as `com/acme/widgets`) move entries into the collapsed or expanded
+/// structure. Parent directories are created as needed and directories left empty are pruned.
+pub fn walk<T>(target: &Path, templater: &T, options: &TemplateOptions) -> Result<TemplatizeResult>
+where
+    T: Templater + ?Sized,
+{
+    Walker::new(templater, options, None, None)?.run(target)
+}
+
+/// Same as [`walk`], but asks the callbacks to confirm every content change and rename.
+///
+/// The content callback receives the file, old contents, new contents and a description;
+/// the path callback receives the old path, new path and the kind of entry being renamed.
+pub fn walk_interactive<T, F, G>(
+    target: &Path,
+    templater: &T,
+    options: &TemplateOptions,
+    content_callback: F,
+    path_callback: G,
+) -> Result<TemplatizeResult>
+where
+    T: Templater + ?Sized,
+    F: Fn(&Path, &str, &str, &str) -> Result<bool>,
+    G: Fn(&Path, &Path, &str) -> Result<bool>,
+{
+    Walker::new(templater, options, Some(&content_callback), Some(&path_callback))?.run(target)
+}
+
+/// Snapshot of a directory entry taken before anything is modified.
+struct Node {
+    name: OsString,
+    children: Option<Vec<Node>>,
+}
+
+impl Node {
+    fn is_dir(&self) -> bool {
+        self.children.is_some()
+    }
+}
+
+/// Where a directory sits while restructuring, all relative to the walk root.
+struct Location {
+    /// Path before any renames, used as the input to the templater.
+    original: PathBuf,
+    /// Path the templater mapped `original` to.
+    planned: PathBuf,
+    /// Path the directory logically occupies after accepted renames.
+    actual: PathBuf,
+    /// Path the directory currently occupies on disk.
+    physical: PathBuf,
+}
+
+struct Walker<'a, T: Templater + ?Sized> {
+    templater: &'a T,
+    options: &'a TemplateOptions,
+    filter: Arc<PathFilter>,
+    fallback_encoding: &'static Encoding,
+    attributes: GitAttributes,
+    content_callback: Option<&'a ContentCallback<'a>>,
+    path_callback: Option<&'a PathCallback<'a>>,
+    /// Directories that a dry run pretends to have created.
+    virtual_dirs: HashSet<PathBuf>,
+    transaction: Transaction,
+    result: TemplatizeResult,
+}
+
+impl<'a, T: Templater + ?Sized> Walker<'a, T> {
+    fn new(
+        templater: &'a T,
+        options: &'a TemplateOptions,
+        content_callback: Option<&'a ContentCallback<'a>>,
+        path_callback: Option<&'a PathCallback<'a>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            templater,
+            options,
+            filter: Arc::new(PathFilter::new(options)?),
+            fallback_encoding: text::fallback_encoding(options.fallback_encoding.as_deref())?,
+            attributes: GitAttributes::new(Path::new("")),
+            content_callback,
+            path_callback,
+            virtual_dirs: HashSet::new(),
+            transaction: Transaction::new(),
+            result: TemplatizeResult::default(),
+        })
+    }
+
+    /// Applies every change, rolling all of them back if any step fails or the run is
+    /// cancelled.
+    fn run(mut self, target: &Path) -> Result<TemplatizeResult> {
+        info!("Starting processing: {:?}", target);
+
+        // The project root whose journal records the run
+        let base = match target.parent() {
+            Some(parent) if target.is_file() => parent.to_path_buf(),
+            _ => target.to_path_buf(),
+        };
+
+        if let Err(e) = self.apply(target).and_then(|()| self.record(&base)) {
+            let changes = self.transaction.changes().len();
+            if changes > 0 {
+                warn!("Rolling back {} changes", changes);
+            }
+            return match self.transaction.rollback() {
+                Ok(()) => Err(e.context("Run failed; all changes were rolled back")),
+                Err(rollback_error) => Err(e.context(format!("{:#}", rollback_error))),
+            };
+        }
+        self.transaction.commit();
+
+        info!(
+            "Processing complete: {} files processed, {} paths renamed, {} content changes",
+            self.result.files_processed, self.result.paths_renamed, self.result.content_changes
+        );
+
+        Ok(self.result)
+    }
+
+    fn apply(&mut self, target: &Path) -> Result<()> {
+        if target.is_file() {
+            let name = Path::new(target.file_name().unwrap_or_default());
+            let matched = glob_path(target, &std::env::current_dir().unwrap_or_default());
+            if self.filter.is_excluded(&matched, false) {
+                info!("Skipping filtered file: {:?}", target);
+                return Ok(());
+            }
+            self.attributes = GitAttributes::new(target.parent().unwrap_or(Path::new("")));
+            self.result.files_processed += 1;
+            if self.options.process_contents && self.filter.allows_contents(&matched) {
+                self.process_contents(target, name)?;
+            }
+            if self.options.process_paths && self.filter.allows_paths(&matched) {
+                self.rename_entry(target, "File")?;
+            }
+        } else if target.is_dir() {
+            let nodes = scan_directory(target, self.options, &self.filter)?;
+            self.attributes = GitAttributes::new(target);
+
+            // First, rewrite contents while every path is still where we found it
+            self.process_tree_contents(target, Path::new(""), &nodes)?;
+
+            // Then move entries into their templated locations, outermost first
+            if self.options.process_paths {
+                let root = Location {
+                    original: PathBuf::new(),
+                    planned: PathBuf::new(),
+                    actual: PathBuf::new(),
+                    physical: PathBuf::new(),
+                };
+                self.restructure(target, &nodes, &root)?;
+
+                // Finally, rename the target directory itself if needed
+                self.rename_entry(target, "Target Directory")?;
+            }
+        } else {
+            anyhow::bail!("Target does not exist or is not a file or directory: {:?}", target);
+        }
+
+        Ok(())
+    }
+
+    /// Records the run in the journal of the project it touched, unless nothing changed.
+    fn record(&self, base: &Path) -> Result<()> {
+        let Some(description) = &self.options.journal else {
+            return Ok(());
+        };
+        let changes = self.transaction.changes();
+        if self.options.dry_run || changes.is_empty() {
+            return Ok(());
+        }
+
+        // Renaming the target directory moves its journal along with it
+        let root = changes
+            .iter()
+            .find_map(|change| match change {
+                Change::Rename { from, to } if from == base => Some(to.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| base.to_path_buf());
+        Journal::new(&root).record(description, changes, base)?;
+        Ok(())
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        if let Some(flag) = &self.options.cancel_flag {
+            if flag.load(Ordering::SeqCst) {
+                anyhow::bail!("Interrupted");
+            }
+        }
+        Ok(())
+    }
+
+    fn process_tree_contents(&mut self, root: &Path, dir: &Path, nodes: &[Node]) -> Result<()> {
+        for node in nodes {
+            let relative = dir.join(&node.name);
+            let path = root.join(&relative);
+            match &node.children {
+                Some(children) => self.process_tree_contents(root, &relative, children)?,
+                None => {
+                    self.check_cancelled()?;
+                    debug!("Processing file: {:?}", path);
+                    self.result.files_processed += 1;
+                    if self.options.process_contents && self.filter.allows_contents(&relative) {
+                        self.process_contents(&path, &relative)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn process_contents(&mut self, file_path: &Path, relative: &Path) -> Result<()> {
+        // Writing through a link would replace it with a regular file
+        if fs::symlink_metadata(file_path)?.file_type().is_symlink() {
+            info!("Skipping symbolic link: {:?}", file_path);
+            self.result.files_skipped += 1;
+            return Ok(());
+        }
+
+        if let Some(limit) = self.options.max_file_size {
+            let size = fs::metadata(file_path)?.len();
+            if size > limit {
+                info!("Skipping file larger than {} bytes ({} bytes): {:?}", limit, size, file_path);
+                self.result.files_skipped += 1;
+                return Ok(());
+            }
+        }
+
+        let bytes = fs::read(file_path)?;
+        let file = match text::decode(&bytes, self.attributes.hint(relative), self.fallback_encoding) {
+            FileKind::Text(file) => file,
+            FileKind::Binary(reason) => {
+                debug!("Skipping binary file ({}): {:?}", reason, file_path);
+                self.result.files_skipped += 1;
+                return Ok(());
+            }
+        };
+        let content = &file.content;
+
+        let new_content = self.templater.process_file_content(relative, content);
+        let protected = self.templater.take_protected();
+        if protected > 0 {
+            warn!(
+                "Left {} occurrence(s) inside existing template syntax in {:?}; use --no-protect to replace them too",
+                protected, file_path
+            );
+            self.result.protected_occurrences += protected;
+        }
+        let Some(new_content) = new_content else {
+            return Ok(());
+        };
+        let new_bytes = match file.encode(&new_content) {
+            Ok(new_bytes) => new_bytes,
+            Err(e) => {
+                warn!("Skipping {:?}: {}", file_path, e);
+                self.result.files_skipped += 1;
+                return Ok(());
+            }
+        };
+
+        if let Some(callback) = self.content_callback {
+            let description = self.templater.content_change_description();
+            if !callback(file_path, content, &new_content, description)? {
+                return Ok(());
+            }
+        }
+
+        if self.options.dry_run {
+            info!("Would update contents of: {:?}", file_path);
+        } else {
+            info!("Updating contents of: {:?}", file_path);
+            self.transaction.write(file_path, &new_bytes)?;
+        }
+        self.templater.content_applied(relative);
+        self.result.content_changes += 1;
+
+        Ok(())
+    }
+
+    /// Moves the children of `dir` to their templated locations, recursing top-down.
+    ///
+    /// When `dir` was folded into an existing directory its children follow it without
+    /// further confirmation. Returns whether anything left `dir`'s physical location, so
+    /// the caller knows to prune it if it ended up empty.
+    fn restructure(&mut self, root: &Path, nodes: &[Node], dir: &Location) -> Result<bool> {
+        let mut vacated = false;
+
+        for node in nodes {
+            self.check_cancelled()?;
+            let original = dir.original.join(&node.name);
+            let planned = self
+                .templater
+                .process_full_path(&original)
+                .unwrap_or_else(|| original.clone());
+
+            // Follow the parent if it was renamed or left in place, unless the replacement
+            // spans the boundary between parent and child
+            let desired = match planned.strip_prefix(&dir.planned) {
+                Ok(rest) => dir.actual.join(rest),
+                Err(_) => planned.clone(),
+            };
+            let current = dir.actual.join(&node.name);
+            let physical = dir.physical.join(&node.name);
+
+            let kind = if node.is_dir() { "Directory" } else { "File" };
+            let mut destination = current;
+            if destination != desired
+                && self.filter.allows_paths(&original)
+                && self.confirm_rename(root, &physical, &desired, kind)? {
+                self.result.paths_renamed += 1;
+                destination = desired;
+            }
+
+            let mut moved_to = physical.clone();
+            if destination != physical && self.relocate(root, &physical, &destination, node.is_dir())? {
+                moved_to = destination.clone();
+                vacated = true;
+            }
+
+            if let Some(children) = &node.children {
+                let location = Location {
+                    original,
+                    planned,
+                    actual: destination,
+                    physical: moved_to.clone(),
+                };
+                let emptied = self.restructure(root, children, &location)?;
+                if emptied && moved_to == physical && self.prune(&root.join(&physical))? {
+                    vacated = true;
+                }
+            }
+        }
+
+        Ok(vacated)
+    }
+
+    fn confirm_rename(&self, root: &Path, from: &Path, to: &Path, kind: &str) -> Result<bool> {
+        match self.path_callback {
+            Some(callback) => callback(&root.join(from), &root.join(to), kind),
+            None => Ok(true),
+        }
+    }
+
+    /// Moves `from` to `to`, creating missing parents. Returns `false` without moving
+    /// anything when `to` is an existing directory that `from` should be merged into.
+    fn relocate(&mut self, root: &Path, from: &Path, to: &Path, is_dir: bool) -> Result<bool> {
+        let source = root.join(from);
+        let destination = root.join(to);
+
+        let existing_dir = destination.is_dir() || self.virtual_dirs.contains(to);
+        if is_dir && existing_dir {
+            debug!("Merging directory: {:?} -> {:?}", source, destination);
+            return Ok(false);
+        }
+        if existing_dir || destination.exists() {
+            anyhow::bail!("Cannot move {:?} to {:?}: destination already exists", source, destination);
+        }
+
+        let kind = if is_dir { "directory" } else { "file" };
+        if self.options.dry_run {
+            info!("Would rename {}: {:?} -> {:?}", kind, source, destination);
+            if is_dir {
+                self.virtual_dirs.insert(to.to_path_buf());
+            }
+            return Ok(true);
+        }
+
+        info!("Renaming {}: {:?} -> {:?}", kind, source, destination);
+        if destination.starts_with(&source) {
+            // Expanding a directory into a path beneath itself needs a detour
+            let mut staging_name = OsString::from(".");
+            staging_name.push(source.file_name().unwrap_or_default());
+            staging_name.push(".templatize");
+            let staging = source.with_file_name(staging_name);
+            self.transaction.rename(&source, &staging)?;
+            if let Some(parent) = destination.parent() {
+                self.transaction.create_dir_all(parent)?;
+            }
+            self.transaction.rename(&staging, &destination)?;
+        } else {
+            if let Some(parent) = destination.parent() {
+                self.transaction.create_dir_all(parent)?;
+            }
+            self.transaction.rename(&source, &destination)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Removes `dir` if a restructure left it empty. Returns whether it was removed.
+    fn prune(&mut self, dir: &Path) -> Result<bool> {
+        if self.options.dry_run || fs::read_dir(dir)?.next().is_some() {
+            return Ok(false);
+        }
+        debug!("Pruning empty directory: {:?}", dir);
+        self.transaction.remove_dir(dir)?;
+        Ok(true)
+    }
+
+    fn rename_entry(&mut self, path: &Path, change_type: &str) -> Result<()> {
+        let Some(new_name) = self.templater.process_path_component(path) else {
+            return Ok(());
+        };
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+        let new_path = parent.join(&new_name);
+
+        if let Some(callback) = self.path_callback {
+            if !callback(path, &new_path, change_type)? {
+                return Ok(());
+            }
+        }
+
+        let kind = change_type.to_lowercase();
+        if self.options.dry_run {
+            info!("Would rename {}: {:?} -> {:?}", kind, path, new_path);
+        } else {
+            info!("Renaming {}: {:?} -> {:?}", kind, path, new_path);
+            self.transaction.rename(path, &new_path)?;
+        }
+        self.result.paths_renamed += 1;
+
+        Ok(())
+    }
+}
+
+/// The path a file target is matched against the globs by: relative to `base`, the current
+/// directory, as if the walk had started there, or just its name when it lies elsewhere.
+fn glob_path(target: &Path, base: &Path) -> PathBuf {
+    let absolute = base.join(target);
+    let beneath = absolute.components().all(|component| component != Component::ParentDir);
+    match absolute.strip_prefix(base) {
+        Ok(relative) if beneath => relative.to_path_buf(),
+        _ => PathBuf::from(target.file_name().unwrap_or_default()),
+    }
+}
+
+/// Snapshots the tree beneath `dir`, skipping VCS metadata, filtered entries and, unless
+/// disabled, ignored entries.
+fn scan_directory(dir: &Path, options: &TemplateOptions, filter: &Arc<PathFilter>) -> Result<Vec<Node>> {
+    let root = dir.to_path_buf();
+    let entry_filter = Arc::clone(filter);
+    let mut builder = WalkBuilder::new(dir);
+    builder
+        .standard_filters(!options.no_ignore)
+        .hidden(false)
+        .require_git(false)
+        .follow_links(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            if is_vcs_directory(entry.file_name()) || entry.file_name() == JOURNAL_DIRECTORY {
+                return false;
+            }
+            let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+            match entry.path().strip_prefix(&root) {
+                Ok(relative) if !relative.as_os_str().is_empty() => {
+                    !entry_filter.is_excluded(relative, is_dir)
+                }
+                _ => true,
+            }
+        });
+    if !options.no_ignore {
+        builder.add_custom_ignore_filename(IGNORE_FILE_NAME);
+    }
+
+    // Entries arrive depth-first, so the stack always mirrors the current directory chain
+    let mut stack: Vec<(OsString, Vec<Node>)> = vec![(OsString::new(), Vec::new())];
+    for entry in builder.build() {
+        let entry = entry?;
+        let depth = entry.depth();
+        if depth == 0 {
+            continue;
+        }
+        while stack.len() > depth {
+            close_directory(&mut stack, filter);
+        }
+
+        let name = entry.file_name().to_os_string();
+        match entry.file_type() {
+            Some(file_type) if file_type.is_dir() => stack.push((name, Vec::new())),
+            // Links are renamed like files, but never followed or written through
+            Some(file_type) if file_type.is_file() || file_type.is_symlink() => {
+                if let Some((_, nodes)) = stack.last_mut() {
+                    nodes.push(Node { name, children: None });
+                }
+            }
+            _ => debug!("Skipping special file: {:?}", entry.path()),
+        }
+    }
+    while stack.len() > 1 {
+        close_directory(&mut stack, filter);
+    }
+
+    Ok(stack.pop().map(|(_, nodes)| nodes).unwrap_or_default())
+}
+
+fn close_directory(stack: &mut Vec<(OsString, Vec<Node>)>, filter: &PathFilter) {
+    if let Some((name, children)) = stack.pop() {
+        // With include globs, directories holding no included files are not part of the walk
+        if children.is_empty() && filter.restricts_files() {
+            return;
+        }
+        if let Some((_, nodes)) = stack.last_mut() {
+            nodes.push(Node {
+                name,
+                children: Some(children),
+            });
+        }
+    }
+}
+
+fn is_vcs_directory(name: &OsStr) -> bool {
+    VCS_DIRECTORIES.iter().any(|vcs| name == *vcs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templater::{CaseShapeTemplater, ExactTemplater, JinjaEscaper};
+    use std::cell::RefCell;
+
+    fn options(process_paths: bool, process_contents: bool, dry_run: bool) -> TemplateOptions {
+        TemplateOptions {
+            process_paths,
+            process_contents,
+            dry_run,
+            ..TemplateOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_walk_renames_and_rewrites() {
+        let temp = tempfile::tempdir().unwrap();
+        let nested = temp.path().join("example-name").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("example-name.txt"), "hello example-name").unwrap();
+
+        let templater = ExactTemplater::new("example-name", "{{ project-name }}");
+        let result = walk(temp.path(), &templater, &options(true, true, false)).unwrap();
+
+        let renamed = temp.path().join("{{ project-name }}").join("src").join("{{ project-name }}.txt");
+        assert_eq!(fs::read_to_string(renamed).unwrap(), "hello {{ project-name }}");
+        assert_eq!(result.files_processed, 1);
+        assert_eq!(result.paths_renamed, 2);
+        assert_eq!(result.content_changes, 1);
+    }
+
+    #[test]
+    fn test_walk_dry_run_leaves_tree_untouched() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("ExampleName.java");
+        fs::write(&file, "class ExampleName {}").unwrap();
+
+        let templater = CaseShapeTemplater::new("example-name", "{{ project-name }}").unwrap();
+        let result = walk(temp.path(), &templater, &options(true, true, true)).unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "class ExampleName {}");
+        assert_eq!(result.paths_renamed, 1);
+        assert_eq!(result.content_changes, 1);
+    }
+
+    #[test]
+    fn test_walk_collapses_multi_segment_paths() {
+        let temp = tempfile::tempdir().unwrap();
+        let package = temp.path().join("src/main/java/com/acme/widgets");
+        fs::create_dir_all(package.join("entities")).unwrap();
+        fs::write(package.join("entities/User.java"), "class User {}").unwrap();
+        fs::write(package.join("App.java"), "class App {}").unwrap();
+
+        let templater = ExactTemplater::new("com/acme/widgets", "{{ package_root }}");
+        let result = walk(temp.path(), &templater, &options(true, false, false)).unwrap();
+
+        let collapsed = temp.path().join("src/main/java/{{ package_root }}");
+        assert!(collapsed.join("entities/User.java").is_file());
+        assert!(collapsed.join("App.java").is_file());
+        assert!(!temp.path().join("src/main/java/com").exists());
+        assert_eq!(result.paths_renamed, 1);
+    }
+
+    #[test]
+    fn test_walk_expands_into_nested_directories() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("src/widgets")).unwrap();
+        fs::write(temp.path().join("src/widgets/__init__.py"), "").unwrap();
+
+        let templater = ExactTemplater::new("widgets", "acme/widgets");
+        walk(temp.path(), &templater, &options(true, false, false)).unwrap();
+
+        assert!(temp.path().join("src/acme/widgets/__init__.py").is_file());
+        assert!(!temp.path().join("src/widgets").exists());
+    }
+
+    #[test]
+    fn test_walk_expands_directory_beneath_itself() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("api")).unwrap();
+        fs::write(temp.path().join("api/mod.rs"), "").unwrap();
+
+        let templater = ExactTemplater::new("api", "api/v1");
+        walk(temp.path(), &templater, &options(true, false, false)).unwrap();
+
+        assert!(temp.path().join("api/v1/mod.rs").is_file());
+    }
+
+    #[test]
+    fn test_walk_merges_into_existing_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("com/acme")).unwrap();
+        fs::create_dir_all(temp.path().join("pkg")).unwrap();
+        fs::write(temp.path().join("com/acme/A.java"), "").unwrap();
+        fs::write(temp.path().join("pkg/B.java"), "").unwrap();
+
+        let templater = ExactTemplater::new("com/acme", "pkg");
+        walk(temp.path(), &templater, &options(true, false, false)).unwrap();
+
+        assert!(temp.path().join("pkg/A.java").is_file());
+        assert!(temp.path().join("pkg/B.java").is_file());
+        assert!(!temp.path().join("com").exists());
+    }
+
+    #[test]
+    fn test_walk_dry_run_restructure_leaves_tree_untouched() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("com/acme/widgets")).unwrap();
+        fs::write(temp.path().join("com/acme/widgets/App.java"), "").unwrap();
+
+        let templater = ExactTemplater::new("com/acme/widgets", "{{ package_root }}");
+        let result = walk(temp.path(), &templater, &options(true, false, true)).unwrap();
+
+        assert!(temp.path().join("com/acme/widgets/App.java").is_file());
+        assert!(!temp.path().join("{{ package_root }}").exists());
+        assert_eq!(result.paths_renamed, 1);
+    }
+
+    #[test]
+    fn test_walk_rejected_directory_keeps_children_in_place() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("example-name")).unwrap();
+        fs::write(temp.path().join("example-name/example-name.txt"), "").unwrap();
+
+        let templater = ExactTemplater::new("example-name", "{{ name }}");
+        walk_interactive(
+            temp.path(),
+            &templater,
+            &options(true, false, false),
+            |_: &Path, _: &str, _: &str, _: &str| Ok(true),
+            |_: &Path, _: &Path, change_type: &str| Ok(change_type == "File"),
+        )
+        .unwrap();
+
+        assert!(temp.path().join("example-name/{{ name }}.txt").is_file());
+    }
+
+    #[test]
+    fn test_walk_honors_ignore_files() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("target")).unwrap();
+        fs::write(temp.path().join(".gitignore"), "target/\n").unwrap();
+        fs::write(temp.path().join(IGNORE_FILE_NAME), "fixtures.txt\n").unwrap();
+        fs::write(temp.path().join("target/out.txt"), "example").unwrap();
+        fs::write(temp.path().join("fixtures.txt"), "example").unwrap();
+        fs::write(temp.path().join("main.txt"), "example").unwrap();
+
+        let templater = ExactTemplater::new("example", "{{ name }}");
+        let result = walk(temp.path(), &templater, &options(false, true, false)).unwrap();
+
+        assert_eq!(result.content_changes, 1);
+        assert_eq!(fs::read_to_string(temp.path().join("main.txt")).unwrap(), "{{ name }}");
+        assert_eq!(fs::read_to_string(temp.path().join("target/out.txt")).unwrap(), "example");
+        assert_eq!(fs::read_to_string(temp.path().join("fixtures.txt")).unwrap(), "example");
+    }
+
+    #[test]
+    fn test_walk_no_ignore_still_skips_vcs_directories() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join(".git")).unwrap();
+        fs::write(temp.path().join(".gitignore"), "ignored.txt\n").unwrap();
+        fs::write(temp.path().join(".git/config"), "example").unwrap();
+        fs::write(temp.path().join("ignored.txt"), "example").unwrap();
+
+        let templater = ExactTemplater::new("example", "{{ name }}");
+        let options = TemplateOptions {
+            no_ignore: true,
+            ..options(false, true, false)
+        };
+        walk(temp.path(), &templater, &options).unwrap();
+
+        assert_eq!(fs::read_to_string(temp.path().join("ignored.txt")).unwrap(), "{{ name }}");
+        assert_eq!(fs::read_to_string(temp.path().join(".git/config")).unwrap(), "example");
+    }
+
+    #[test]
+    fn test_walk_applies_glob_filters_and_scopes() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("src/Acme.java"), "Acme").unwrap();
+        fs::write(temp.path().join("Acme.md"), "Acme").unwrap();
+        fs::write(temp.path().join("Acme.lock"), "Acme").unwrap();
+
+        let templater = ExactTemplater::new("Acme", "{{ name }}");
+        let options = TemplateOptions {
+            exclude: vec!["*.lock".to_string()],
+            content_scope: vec!["**/*.java".to_string()],
+            path_scope: vec!["src/**".to_string()],
+            ..options(true, true, false)
+        };
+        let result = walk(temp.path(), &templater, &options).unwrap();
+
+        assert_eq!(result.files_processed, 2);
+        assert_eq!(fs::read_to_string(temp.path().join("src/{{ name }}.java")).unwrap(), "{{ name }}");
+        assert_eq!(fs::read_to_string(temp.path().join("Acme.md")).unwrap(), "Acme");
+        assert_eq!(fs::read_to_string(temp.path().join("Acme.lock")).unwrap(), "Acme");
+    }
+
+    #[test]
+    fn test_walk_applies_glob_filters_to_file_targets() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("Acme.lock"), "Acme").unwrap();
+        fs::write(temp.path().join("Acme.md"), "Acme").unwrap();
+
+        let templater = ExactTemplater::new("Acme", "{{ name }}");
+        let excluded = TemplateOptions {
+            exclude: vec!["*.lock".to_string()],
+            ..options(true, true, false)
+        };
+        let result = walk(&temp.path().join("Acme.lock"), &templater, &excluded).unwrap();
+        assert_eq!(result.files_processed, 0);
+        assert_eq!(fs::read_to_string(temp.path().join("Acme.lock")).unwrap(), "Acme");
+
+        let not_included = TemplateOptions {
+            include: vec!["*.java".to_string()],
+            ..options(true, true, false)
+        };
+        let result = walk(&temp.path().join("Acme.md"), &templater, &not_included).unwrap();
+        assert_eq!(result.files_processed, 0);
+        assert_eq!(fs::read_to_string(temp.path().join("Acme.md")).unwrap(), "Acme");
+
+        let included = TemplateOptions {
+            include: vec!["*.md".to_string()],
+            ..options(true, true, false)
+        };
+        let result = walk(&temp.path().join("Acme.md"), &templater, &included).unwrap();
+        assert_eq!(result.files_processed, 1);
+        assert_eq!(fs::read_to_string(temp.path().join("{{ name }}.md")).unwrap(), "{{ name }}");
+    }
+
+    #[test]
+    fn test_walk_applies_directory_globs_to_file_targets() {
+        // File targets are matched relative to the current directory
+        let temp = tempfile::tempdir_in(std::env::current_dir().unwrap()).unwrap();
+        fs::create_dir_all(temp.path().join("src/main")).unwrap();
+        fs::write(temp.path().join("src/main/Acme.java"), "Acme").unwrap();
+        fs::write(temp.path().join("Acme.java"), "Acme").unwrap();
+
+        let templater = ExactTemplater::new("Acme", "{{ name }}");
+        let options = TemplateOptions {
+            include: vec!["**/src/**".to_string()],
+            content_scope: vec!["**/main/**/*.java".to_string()],
+            ..options(false, true, false)
+        };
+        let result = walk(&temp.path().join("src/main/Acme.java"), &templater, &options).unwrap();
+        assert_eq!(result.content_changes, 1);
+        assert_eq!(fs::read_to_string(temp.path().join("src/main/Acme.java")).unwrap(), "{{ name }}");
+
+        let result = walk(&temp.path().join("Acme.java"), &templater, &options).unwrap();
+        assert_eq!(result.files_processed, 0);
+        assert_eq!(fs::read_to_string(temp.path().join("Acme.java")).unwrap(), "Acme");
+    }
+
+    #[test]
+    fn test_glob_path_is_relative_to_the_current_directory() {
+        let base = Path::new("/work/project");
+        assert_eq!(glob_path(Path::new("src/Main.java"), base), Path::new("src/Main.java"));
+        assert_eq!(glob_path(Path::new("./src/Main.java"), base), Path::new("src/Main.java"));
+        assert_eq!(glob_path(Path::new("/work/project/src/Main.java"), base), Path::new("src/Main.java"));
+        assert_eq!(glob_path(Path::new("/elsewhere/src/Main.java"), base), Path::new("Main.java"));
+        assert_eq!(glob_path(Path::new("../other/Main.java"), base), Path::new("Main.java"));
+    }
+
+    #[test]
+    fn test_walk_transcodes_and_skips_binaries() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("latin1.txt"), b"Acme caf\xe9").unwrap();
+        fs::write(temp.path().join("utf16.txt"), b"\xff\xfeA\x00c\x00m\x00e\x00").unwrap();
+        fs::write(temp.path().join("image.png"), b"\x89PNG\r\n\x1a\nAcme").unwrap();
+        fs::write(temp.path().join("large.txt"), "Acme ".repeat(100)).unwrap();
+
+        let templater = ExactTemplater::new("Acme", "{{ org }}");
+        let options = TemplateOptions {
+            max_file_size: Some(64),
+            ..options(false, true, false)
+        };
+        let result = walk(temp.path(), &templater, &options).unwrap();
+
+        assert_eq!(fs::read(temp.path().join("latin1.txt")).unwrap(), b"{{ org }} caf\xe9");
+        assert_eq!(
+            fs::read(temp.path().join("utf16.txt")).unwrap(),
+            b"\xff\xfe{\x00{\x00 \x00o\x00r\x00g\x00 \x00}\x00}\x00"
+        );
+        assert_eq!(fs::read(temp.path().join("image.png")).unwrap(), b"\x89PNG\r\n\x1a\nAcme");
+        assert_eq!(result.content_changes, 2);
+        assert_eq!(result.files_skipped, 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_preserves_permissions_and_line_endings() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let script = temp.path().join("gradlew");
+        fs::write(&script, "#!/bin/sh\r\necho acme\r\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let templater = ExactTemplater::new("acme", "{{ org }}\n# org");
+        walk(temp.path(), &templater, &options(false, true, false)).unwrap();
+
+        assert_eq!(fs::read_to_string(&script).unwrap(), "#!/bin/sh\r\necho {{ org }}\r\n# org\r\n");
+        assert_eq!(fs::metadata(&script).unwrap().permissions().mode() & 0o777, 0o755);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_skips_symlinked_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let project = temp.path().join("proj");
+        fs::create_dir_all(temp.path().join("outside")).unwrap();
+        fs::create_dir_all(&project).unwrap();
+        fs::write(temp.path().join("outside/secret.txt"), "acme").unwrap();
+        std::os::unix::fs::symlink("../outside/secret.txt", project.join("link.txt")).unwrap();
+        fs::write(project.join("real.txt"), "acme").unwrap();
+
+        let templater = ExactTemplater::new("acme", "{{ org }}");
+        let result = walk(&project, &templater, &options(false, true, false)).unwrap();
+
+        assert_eq!(result.content_changes, 1);
+        assert_eq!(result.files_skipped, 1);
+        assert!(fs::symlink_metadata(project.join("link.txt")).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(temp.path().join("outside/secret.txt")).unwrap(), "acme");
+        assert_eq!(fs::read_to_string(project.join("real.txt")).unwrap(), "{{ org }}");
+
+        let result = walk(&project.join("link.txt"), &templater, &options(false, true, false)).unwrap();
+        assert_eq!(result.files_skipped, 1);
+        assert!(fs::symlink_metadata(project.join("link.txt")).unwrap().file_type().is_symlink());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_does_not_follow_symlinked_directories() {
+        let temp = tempfile::tempdir().unwrap();
+        let project = temp.path().join("proj");
+        fs::create_dir_all(temp.path().join("outside/acme")).unwrap();
+        fs::create_dir_all(&project).unwrap();
+        fs::write(temp.path().join("outside/acme/acme.txt"), "acme").unwrap();
+        std::os::unix::fs::symlink("../outside", project.join("shared")).unwrap();
+
+        let templater = ExactTemplater::new("acme", "{{ org }}");
+        let result = walk(&project, &templater, &options(true, true, false)).unwrap();
+
+        assert_eq!(result.content_changes, 0);
+        assert_eq!(result.paths_renamed, 0);
+        assert!(fs::symlink_metadata(project.join("shared")).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(temp.path().join("outside/acme/acme.txt")).unwrap(), "acme");
+    }
+
+    #[test]
+    fn test_walk_rolls_back_on_failure() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("acme")).unwrap();
+        fs::write(temp.path().join("acme/x.txt"), "acme").unwrap();
+        fs::write(temp.path().join("acme.txt"), "acme").unwrap();
+        fs::write(temp.path().join("{{ org }}.txt"), "taken").unwrap();
+
+        let templater = ExactTemplater::new("acme", "{{ org }}");
+        let error = walk(temp.path(), &templater, &options(true, true, false)).unwrap_err();
+
+        assert!(format!("{:#}", error).contains("destination already exists"));
+        assert_eq!(fs::read_to_string(temp.path().join("acme/x.txt")).unwrap(), "acme");
+        assert_eq!(fs::read_to_string(temp.path().join("acme.txt")).unwrap(), "acme");
+        assert_eq!(fs::read_to_string(temp.path().join("{{ org }}.txt")).unwrap(), "taken");
+        assert!(!temp.path().join("{{ org }}").exists());
+    }
+
+    #[test]
+    fn test_walk_stops_when_cancelled() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("acme.txt"), "acme").unwrap();
+
+        let templater = ExactTemplater::new("acme", "{{ org }}");
+        let options = TemplateOptions {
+            cancel_flag: Some(Arc::new(std::sync::atomic::AtomicBool::new(true))),
+            ..options(true, true, false)
+        };
+        let error = walk(temp.path(), &templater, &options).unwrap_err();
+
+        assert!(format!("{:#}", error).contains("Interrupted"));
+        assert_eq!(fs::read_to_string(temp.path().join("acme.txt")).unwrap(), "acme");
+    }
+
+    #[test]
+    fn test_walk_records_journal_that_can_be_undone() {
+        let temp = tempfile::tempdir().unwrap();
+        let project = temp.path().join("acme");
+        fs::create_dir_all(project.join("acme")).unwrap();
+        fs::write(project.join("acme/acme.txt"), "acme").unwrap();
+
+        let templater = ExactTemplater::new("acme", "{{ org }}");
+        let options = TemplateOptions {
+            journal: Some("exact acme {{ org }}".to_string()),
+            ..options(true, true, false)
+        };
+        walk(&project, &templater, &options).unwrap();
+
+        let renamed = temp.path().join("{{ org }}");
+        assert!(renamed.join("{{ org }}/{{ org }}.txt").is_file());
+        let journal = Journal::new(&renamed);
+        assert_eq!(journal.operations().unwrap().len(), 1);
+
+        // The journal directory itself is never templatized
+        walk(&renamed, &ExactTemplater::new("journal", "x"), &options).unwrap();
+        assert_eq!(journal.operations().unwrap().len(), 1);
+
+        let (_, root) = journal.undo(1, false).unwrap();
+        assert_eq!(root, project);
+        assert_eq!(fs::read_to_string(project.join("acme/acme.txt")).unwrap(), "acme");
+        assert!(!project.join("{{ org }}").exists());
+    }
+
+    #[test]
+    fn test_walk_interactive_respects_rejections() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("a.txt"), "{{ value }}").unwrap();
+        fs::write(temp.path().join("b.txt"), "{{ value }}").unwrap();
+
+        let seen = RefCell::new(Vec::new());
+        let escaper = JinjaEscaper::new();
+        let result = walk_interactive(
+            temp.path(),
+            &escaper,
+            &options(false, true, false),
+            |path: &Path, _: &str, _: &str, description: &str| {
+                seen.borrow_mut().push(description.to_string());
+                Ok(path.ends_with("a.txt"))
+            },
+            |_: &Path, _: &Path, _: &str| Ok(true),
+        )
+        .unwrap();
+
+        assert_eq!(seen.borrow().as_slice(), ["Jinja escaping", "Jinja escaping"]);
+        assert_eq!(result.content_changes, 1);
+        let reports = escaper.take_reports();
+        assert_eq!(reports.iter().map(|report| report.path.as_path()).collect::<Vec<_>>(), [Path::new("a.txt")]);
+        assert_eq!(fs::read_to_string(temp.path().join("a.txt")).unwrap(), "{{'{'}}{ value }}");
+        assert_eq!(fs::read_to_string(temp.path().join("b.txt")).unwrap(), "{{ value }}");
+    }
+}
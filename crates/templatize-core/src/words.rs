@@ -0,0 +1,150 @@
+/// Splits identifiers into words and capitalizes them, honoring a dictionary of acronyms.
+///
+/// Words break at any character that isn't a letter or digit, between a lowercase letter and
+/// an uppercase one (`userName`), before the last capital of an uppercase run that continues in
+/// lowercase (`HTTPServer`), and between a digit and an uppercase letter (`v2Api`). Digits stay
+/// with the word they follow, so `oauth2-client` and `OAuth2Client` are both `oauth2 client`.
+///
+/// Acronyms such as `OAuth` are matched in any casing at the start of a word, and are written
+/// with their dictionary spelling wherever a shape capitalizes words.
+#[derive(Debug, Clone, Default)]
+pub struct WordSplitter {
+    /// Acronyms as given, longest first so `OAuth` wins over `O`.
+    acronyms: Vec<String>,
+}
+
+impl WordSplitter {
+    pub fn new<S: AsRef<str>>(acronyms: &[S]) -> Self {
+        let mut acronyms: Vec<String> = acronyms
+            .iter()
+            .map(|acronym| acronym.as_ref().trim().to_string())
+            .filter(|acronym| !acronym.is_empty())
+            .collect();
+        acronyms.sort_by_key(|acronym| std::cmp::Reverse(acronym.chars().count()));
+        Self { acronyms }
+    }
+
+    /// The lowercase words of `text`.
+    pub fn split(&self, text: &str) -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|segment| !segment.is_empty())
+            .flat_map(|segment| self.split_segment(segment))
+            .collect()
+    }
+
+    fn split_segment(&self, segment: &str) -> Vec<String> {
+        let chars: Vec<char> = segment.chars().collect();
+        let mut words = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let end = self
+                .acronym_at(&chars[start..])
+                .map_or_else(|| start + word_length(&chars[start..]), |len| start + len);
+            words.push(chars[start..end].iter().collect::<String>().to_lowercase());
+            start = end;
+        }
+        words
+    }
+
+    /// Length of the acronym starting `chars`, with any digits or plural `s` following it,
+    /// provided the acronym isn't just the start of a longer lowercase word.
+    fn acronym_at(&self, chars: &[char]) -> Option<usize> {
+        self.acronyms.iter().find_map(|acronym| {
+            let mut len = 0;
+            for expected in acronym.chars() {
+                let actual = chars.get(len)?;
+                if !actual.to_lowercase().eq(expected.to_lowercase()) {
+                    return None;
+                }
+                len += 1;
+            }
+            while chars.get(len).is_some_and(char::is_ascii_digit) {
+                len += 1;
+            }
+            if chars.get(len) == Some(&'s') && !chars.get(len + 1).is_some_and(|c| c.is_lowercase()) {
+                len += 1;
+            }
+            (!chars.get(len).is_some_and(|c| c.is_lowercase())).then_some(len)
+        })
+    }
+
+    /// `word` with its first letter capitalized, or with its dictionary spelling if it is an
+    /// acronym (keeping any digits or plural `s` after it).
+    pub fn capitalize(&self, word: &str) -> String {
+        if let Some((acronym, suffix)) = self.acronym_of(word) {
+            return format!("{}{}", acronym, suffix);
+        }
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+
+    /// Whether `word` is in the acronym dictionary, ignoring digits or a plural `s` after it.
+    pub fn is_acronym(&self, word: &str) -> bool {
+        self.acronym_of(word).is_some()
+    }
+
+    /// The acronym a lowercase `word` starts with, and the digits or plural `s` after it.
+    fn acronym_of<'a>(&'a self, word: &'a str) -> Option<(&'a str, &'a str)> {
+        self.acronyms.iter().find_map(|acronym| {
+            let head = word.get(..acronym.len())?;
+            let suffix = &word[acronym.len()..];
+            let plain_suffix = suffix == "s" || suffix.chars().all(|c| c.is_ascii_digit());
+            (head == acronym.to_lowercase() && plain_suffix).then_some((acronym.as_str(), suffix))
+        })
+    }
+}
+
+/// Length of the word at the start of `chars`, which holds no separators.
+fn word_length(chars: &[char]) -> usize {
+    for i in 1..chars.len() {
+        let (prev, cur) = (chars[i - 1], chars[i]);
+        let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+        let breaks = (prev.is_lowercase() && cur.is_uppercase())
+            || (prev.is_uppercase() && cur.is_uppercase() && next_is_lower)
+            || (prev.is_numeric() && cur.is_uppercase());
+        if breaks {
+            return i;
+        }
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_without_acronyms() {
+        let splitter = WordSplitter::default();
+        assert_eq!(splitter.split("HTTPServer"), ["http", "server"]);
+        assert_eq!(splitter.split("XMLParser"), ["xml", "parser"]);
+        assert_eq!(splitter.split("oauth2-client"), ["oauth2", "client"]);
+        assert_eq!(splitter.split("v2Api"), ["v2", "api"]);
+        assert_eq!(splitter.split("user_id"), ["user", "id"]);
+        assert_eq!(splitter.split("Base64Encoder"), ["base64", "encoder"]);
+        assert_eq!(splitter.split("my project.name"), ["my", "project", "name"]);
+    }
+
+    #[test]
+    fn test_split_with_acronyms() {
+        let splitter = WordSplitter::new(&["ID", "URL", "API", "OAuth"]);
+        assert_eq!(splitter.split("OAuth2Client"), ["oauth2", "client"]);
+        assert_eq!(splitter.split("UserIDService"), ["user", "id", "service"]);
+        assert_eq!(splitter.split("baseURLs"), ["base", "urls"]);
+        assert_eq!(splitter.split("Identity"), ["identity"]);
+    }
+
+    #[test]
+    fn test_capitalize() {
+        let splitter = WordSplitter::new(&["ID", "OAuth"]);
+        assert_eq!(splitter.capitalize("oauth2"), "OAuth2");
+        assert_eq!(splitter.capitalize("ids"), "IDs");
+        assert_eq!(splitter.capitalize("id"), "ID");
+        assert_eq!(splitter.capitalize("idea"), "Idea");
+        assert!(splitter.is_acronym("oauth"));
+        assert!(!splitter.is_acronym("client"));
+    }
+}